@@ -1,5 +1,8 @@
 use lazy_static::lazy_static;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 
 #[derive(Debug)]
 struct TreeMap {
@@ -9,13 +12,109 @@ struct TreeMap {
 }
 
 impl TreeMap {
-    fn trees_encountered(&self, slope: (usize, usize)) -> usize {
-        std::iter::successors(Some((0, 0)), |pos| {
-            Some(((pos.0 + slope.0) % self.width, pos.1 + slope.1))
+    fn has_tree(&self, (x, y): (usize, usize)) -> bool {
+        self.trees.contains(&(x % self.width, y))
+    }
+
+    fn path(&self, slope: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        std::iter::successors(Some((0, 0)), move |pos| {
+            Some((pos.0 + slope.0, pos.1 + slope.1))
         })
-        .take_while(|&(_, y)| y < self.height)
-        .filter(|pos| self.trees.contains(pos))
-        .count()
+        .take_while(move |&(_, y)| y < self.height)
+    }
+
+    fn trees_encountered(&self, slope: (usize, usize)) -> usize {
+        self.path(slope).filter(|&pos| self.has_tree(pos)).count()
+    }
+
+    fn render(&self, slope: (usize, usize)) -> PathRender<'_> {
+        let path: BTreeSet<_> = self.path(slope).collect();
+        let tiles = path
+            .iter()
+            .map(|&(x, _)| x / self.width + 1)
+            .max()
+            .unwrap_or(1);
+        PathRender {
+            map: self,
+            path,
+            width: tiles * self.width,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Cell {
+    Open,
+    Tree,
+    Hit,
+    Miss,
+}
+
+impl Cell {
+    fn symbol(self) -> char {
+        match self {
+            Cell::Open => '.',
+            Cell::Tree => '#',
+            Cell::Hit => 'X',
+            Cell::Miss => 'O',
+        }
+    }
+
+    fn color(self) -> [u8; 3] {
+        match self {
+            Cell::Open => [255, 255, 255],
+            Cell::Tree => [34, 139, 34],
+            Cell::Hit => [220, 20, 60],
+            Cell::Miss => [30, 144, 255],
+        }
+    }
+}
+
+struct PathRender<'a> {
+    map: &'a TreeMap,
+    path: BTreeSet<(usize, usize)>,
+    width: usize,
+}
+
+impl PathRender<'_> {
+    fn cell(&self, pos: (usize, usize)) -> Cell {
+        match (self.path.contains(&pos), self.map.has_tree(pos)) {
+            (true, true) => Cell::Hit,
+            (true, false) => Cell::Miss,
+            (false, true) => Cell::Tree,
+            (false, false) => Cell::Open,
+        }
+    }
+
+    fn write_ppm(&self, out: &mut impl Write, scale: usize) -> io::Result<()> {
+        write!(
+            out,
+            "P6\n{} {}\n255\n",
+            self.width * scale,
+            self.map.height * scale
+        )?;
+        for y in 0..self.map.height {
+            let row: Vec<u8> = (0..self.width)
+                .flat_map(|x| std::iter::repeat_n(self.cell((x, y)).color(), scale))
+                .flatten()
+                .collect();
+            for _ in 0..scale {
+                out.write_all(&row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PathRender<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.map.height {
+            for x in 0..self.width {
+                write!(f, "{}", self.cell((x, y)).symbol())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
@@ -55,7 +154,40 @@ fn part2() {
     println!("**  {}", tree_product);
 }
 
-fn main() {
-    part1();
-    part2();
+fn parse_slope(right: &str, down: &str) -> Option<(usize, usize)> {
+    let down = down.parse().ok().filter(|&down| down > 0)?;
+    Some((right.parse().ok()?, down))
+}
+
+fn render(args: &[String]) -> io::Result<()> {
+    let slope = match args {
+        [] => Some((3, 1)),
+        [right, down] | [right, down, _] => parse_slope(right, down),
+        _ => None,
+    };
+    let slope = slope.unwrap_or_else(|| {
+        eprintln!("usage: day03 render [RIGHT DOWN [OUT.ppm]] (DOWN must be at least 1)");
+        std::process::exit(1);
+    });
+    let render = TREE_MAP.render(slope);
+    if let Some(path) = args.get(2) {
+        let mut out = BufWriter::new(File::create(path)?);
+        render.write_ppm(&mut out, 4)?;
+        out.flush()
+    } else {
+        print!("{}", render);
+        Ok(())
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((cmd, rest)) if cmd == "render" => render(rest),
+        _ => {
+            part1();
+            part2();
+            Ok(())
+        }
+    }
 }
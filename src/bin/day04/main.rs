@@ -1,104 +1,251 @@
+use either::Either;
 use itertools::Itertools;
-use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::process;
 
+mod schema;
+mod typed;
+
+use schema::{Invalid, Schema};
+use typed::{TypedPassport, Unit};
+
+const DEFAULT_SCHEMA: &str = include_str!("schema.txt");
+
+#[derive(Default)]
 struct Passport {
+    entries: Vec<(String, String)>,
     fields: HashMap<String, String>,
+    malformed: Vec<String>,
 }
 
-const REQUIRED_FIELDS: &[&str] = &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-const EYE_COLORS: &[&str] = &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+enum Problem {
+    Missing(String),
+    Invalid(String, String, Invalid),
+    Duplicate(String),
+    Conflict(String, Vec<String>),
+    Malformed(String),
+    Unknown(String),
+}
 
-impl Passport {
-    fn has_required_fields(&self) -> bool {
-        REQUIRED_FIELDS
-            .iter()
-            .all(|&field| self.fields.contains_key(field))
+impl Problem {
+    fn summary(&self) -> String {
+        match self {
+            Problem::Missing(field) => format!("missing {}", field),
+            Problem::Invalid(field, _, reason) => format!("invalid {}: {}", field, reason),
+            Problem::Duplicate(field) => format!("duplicate {}", field),
+            Problem::Conflict(field, _) => format!("conflicting {}", field),
+            Problem::Malformed(_) => "malformed token".to_owned(),
+            Problem::Unknown(field) => format!("unknown {}", field),
+        }
     }
+}
 
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Invalid(field, value, reason) => {
+                write!(f, "invalid {} {:?}: {}", field, value, reason)
+            }
+            Problem::Conflict(field, values) => {
+                write!(f, "conflicting {}: {:?}", field, values)
+            }
+            Problem::Malformed(token) => write!(f, "malformed token {:?}", token),
+            _ => write!(f, "{}", self.summary()),
+        }
+    }
+}
+
+struct Validation {
+    problems: Vec<Problem>,
+}
+
+impl Validation {
     fn is_valid(&self) -> bool {
-        self.has_required_fields()
-            && REQUIRED_FIELDS
-                .iter()
-                .all(|&field| field_is_valid(field, &self.fields[field]))
+        self.problems.is_empty()
     }
 }
 
-fn field_is_valid(field_name: &str, value: &str) -> bool {
-    match field_name {
-        "byr" => {
-            if let Ok(year) = value.parse() {
-                (1920..=2002).contains(&year)
-            } else {
-                false
-            }
-        }
-        "iyr" => {
-            if let Ok(year) = value.parse() {
-                (2010..=2020).contains(&year)
-            } else {
-                false
+impl Passport {
+    fn parse(entry: &str) -> Self {
+        let mut passport = Passport::default();
+        for token in entry.split_whitespace() {
+            match token.split_once(':') {
+                Some((key, value)) if !key.is_empty() => {
+                    passport.entries.push((key.to_owned(), value.to_owned()));
+                    passport.fields.insert(key.to_owned(), value.to_owned());
+                }
+                _ => passport.malformed.push(token.to_owned()),
             }
         }
-        "eyr" => {
-            if let Ok(year) = value.parse() {
-                (2020..=2030).contains(&year)
-            } else {
-                false
+        passport
+    }
+
+    fn repeated_fields(&self) -> Vec<(&str, Vec<&str>)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .into_group_map()
+            .into_iter()
+            .filter(|(_, values)| values.len() > 1)
+            .sorted()
+            .collect()
+    }
+
+    fn is_well_formed(&self) -> bool {
+        self.malformed.is_empty() && self.entries.len() == self.fields.len()
+    }
+
+    fn has_required_fields(&self, schema: &Schema) -> bool {
+        schema
+            .required()
+            .all(|rule| self.fields.contains_key(&rule.name))
+    }
+
+    fn is_valid(&self, schema: &Schema) -> bool {
+        self.has_required_fields(schema)
+            && schema.fields.iter().all(|rule| {
+                self.fields
+                    .get(&rule.name)
+                    .is_none_or(|value| rule.is_valid(value))
+            })
+    }
+
+    fn validate(&self, schema: &Schema) -> Validation {
+        let mut problems = Vec::new();
+        for rule in &schema.fields {
+            match self.fields.get(&rule.name) {
+                Some(value) => {
+                    if let Err(reason) = rule.check(value) {
+                        problems.push(Problem::Invalid(rule.name.clone(), value.clone(), reason));
+                    }
+                }
+                None if rule.required => problems.push(Problem::Missing(rule.name.clone())),
+                None => {}
             }
         }
-        "hgt" => {
-            let range = if value.ends_with("cm") {
-                150..=193
-            } else if value.ends_with("in") {
-                59..=76
+        for (field, values) in self.repeated_fields() {
+            if values.iter().all_equal() {
+                problems.push(Problem::Duplicate(field.to_owned()));
             } else {
-                return false;
-            };
-            if let Ok(height) = value[..value.len() - 2].parse() {
-                range.contains(&height)
-            } else {
-                false
+                let values = values.into_iter().map(|v| v.to_owned()).collect();
+                problems.push(Problem::Conflict(field.to_owned(), values));
             }
         }
-        "hcl" => {
-            value.len() == 7
-                && value.starts_with('#')
-                && value[1..]
-                    .chars()
-                    .all(|c| ('0'..='9').contains(&c) || ('a'..='f').contains(&c))
-        }
-        "ecl" => EYE_COLORS.contains(&value),
-        "pid" => value.len() == 9 && value.chars().all(|c| ('0'..='9').contains(&c)),
-        _ => true,
+        problems.extend(self.malformed.iter().cloned().map(Problem::Malformed));
+        problems.extend(
+            self.fields
+                .keys()
+                .filter(|field| schema.get(field).is_none())
+                .sorted()
+                .cloned()
+                .map(Problem::Unknown),
+        );
+        Validation { problems }
     }
 }
 
-lazy_static! {
-    static ref PASSPORTS: Vec<Passport> = include_str!("input.txt")
-        .split("\n\n")
-        .map(|entry| {
-            Passport {
-                fields: entry
-                    .split_whitespace()
-                    .flat_map(|field| field.split(':').map(|s| s.to_owned()).collect_tuple())
-                    .collect(),
-            }
-        })
-        .collect();
+fn well_formed(passports: &[Passport], strict: bool) -> impl Iterator<Item = &Passport> {
+    passports
+        .iter()
+        .filter(move |p| !strict || p.is_well_formed())
 }
 
-fn part1() {
-    let count = PASSPORTS.iter().filter(|p| p.has_required_fields()).count();
+fn part1(passports: &[Passport], schema: &Schema, strict: bool) {
+    let count = well_formed(passports, strict)
+        .filter(|p| p.has_required_fields(schema))
+        .count();
     println!("*   {}", count);
 }
 
-fn part2() {
-    let count = PASSPORTS.iter().filter(|p| p.is_valid()).count();
+fn part2(passports: &[Passport], schema: &Schema, strict: bool) {
+    let count = well_formed(passports, strict)
+        .filter(|p| p.is_valid(schema))
+        .count();
     println!("**  {}", count);
 }
 
+fn report(passports: &[Passport], schema: &Schema) {
+    let mut failures = HashMap::new();
+    for (i, passport) in passports.iter().enumerate() {
+        let validation = passport.validate(schema);
+        if validation.is_valid() {
+            continue;
+        }
+        println!("passport {}:", i + 1);
+        for problem in &validation.problems {
+            println!("    {}", problem);
+            *failures.entry(problem.summary()).or_insert(0) += 1;
+        }
+    }
+    println!("most common failures:");
+    for (summary, count) in failures
+        .iter()
+        .sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)))
+        .take(10)
+    {
+        println!("{:>6}  {}", count, summary);
+    }
+}
+
+fn normalize(passports: &[Passport], schema: &Schema, strict: bool, height_unit: Option<Unit>) {
+    let batch = well_formed(passports, strict)
+        .filter(|p| p.is_valid(schema))
+        .filter_map(|p| TypedPassport::try_from(&p.fields).ok())
+        .map(|mut p| {
+            if let Some(unit) = height_unit {
+                p.hgt = p.hgt.to_unit(unit);
+            }
+            p.to_string()
+        })
+        .join("\n\n");
+    println!("{}", batch);
+}
+
 fn main() {
-    part1();
-    part2();
+    let mut schema_path = None;
+    let mut show_report = false;
+    let mut show_normalized = false;
+    let mut strict = false;
+    let mut height_unit = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--report" => show_report = true,
+            "--normalize" => show_normalized = true,
+            "--strict" => strict = true,
+            "--cm" => height_unit = Some(Unit::Cm),
+            "--in" => height_unit = Some(Unit::In),
+            _ if !arg.starts_with("--") && schema_path.is_none() => schema_path = Some(arg),
+            _ => {
+                eprintln!("usage: day04 [--report] [--normalize [--cm|--in]] [--strict] [SCHEMA]");
+                process::exit(1);
+            }
+        }
+    }
+    if height_unit.is_some() && !show_normalized {
+        eprintln!("error: --cm and --in only apply together with --normalize");
+        process::exit(1);
+    }
+    let schema = match &schema_path {
+        Some(path) => Schema::load(path),
+        None => DEFAULT_SCHEMA.parse().map_err(Either::Right),
+    };
+    let schema = schema.unwrap_or_else(|err| {
+        let path = schema_path.as_deref().unwrap_or("built-in schema");
+        eprintln!("error: {}: {}", path, err);
+        process::exit(1);
+    });
+    let passports: Vec<Passport> = include_str!("input.txt")
+        .split("\n\n")
+        .map(Passport::parse)
+        .collect();
+    part1(&passports, &schema, strict);
+    part2(&passports, &schema, strict);
+    if show_report {
+        report(&passports, &schema);
+    }
+    if show_normalized {
+        normalize(&passports, &schema, strict, height_unit);
+    }
 }
@@ -0,0 +1,151 @@
+use either::Either;
+use regex::Regex;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+pub enum FieldKind {
+    Any,
+    Number(RangeInclusive<i64>),
+    Measure(Vec<(String, RangeInclusive<i64>)>),
+    Pattern(Regex),
+    OneOf(Vec<String>),
+}
+
+pub struct FieldRule {
+    pub name: String,
+    pub required: bool,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug)]
+pub enum Invalid {
+    NotANumber,
+    OutOfRange(RangeInclusive<i64>),
+    BadUnit,
+    NoMatch,
+    NotAnOption,
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Invalid::NotANumber => write!(f, "not a number"),
+            Invalid::OutOfRange(range) => {
+                write!(f, "out of range {}..={}", range.start(), range.end())
+            }
+            Invalid::BadUnit => write!(f, "bad unit"),
+            Invalid::NoMatch => write!(f, "bad format"),
+            Invalid::NotAnOption => write!(f, "not a recognized value"),
+        }
+    }
+}
+
+fn check_number(value: &str, range: &RangeInclusive<i64>) -> Result<(), Invalid> {
+    let n = value.parse().map_err(|_| Invalid::NotANumber)?;
+    if range.contains(&n) {
+        Ok(())
+    } else {
+        Err(Invalid::OutOfRange(range.clone()))
+    }
+}
+
+impl FieldRule {
+    pub fn check(&self, value: &str) -> Result<(), Invalid> {
+        match &self.kind {
+            FieldKind::Any => Ok(()),
+            FieldKind::Number(range) => check_number(value, range),
+            FieldKind::Measure(units) => units
+                .iter()
+                .find_map(|(unit, range)| value.strip_suffix(unit.as_str()).map(|n| (n, range)))
+                .map_or(Err(Invalid::BadUnit), |(n, range)| check_number(n, range)),
+            FieldKind::Pattern(regex) if regex.is_match(value) => Ok(()),
+            FieldKind::Pattern(_) => Err(Invalid::NoMatch),
+            FieldKind::OneOf(options) if options.iter().any(|option| option == value) => Ok(()),
+            FieldKind::OneOf(_) => Err(Invalid::NotAnOption),
+        }
+    }
+
+    pub fn is_valid(&self, value: &str) -> bool {
+        self.check(value).is_ok()
+    }
+}
+
+pub struct Schema {
+    pub fields: Vec<FieldRule>,
+}
+
+peg::parser! {
+    grammar schema() for str {
+        rule _() = [' ' | '\t']+
+
+        rule word() -> String
+            = w:$(['a'..='z' | 'A'..='Z' | '0'..='9' | '_']+) { w.to_owned() }
+
+        rule number() -> i64
+            = n:$("-"? ['0'..='9']+) {? n.parse().or(Err("i64")) }
+
+        rule range() -> RangeInclusive<i64>
+            = lo:number() "..=" hi:number() { lo..=hi }
+
+        rule unit() -> (String, RangeInclusive<i64>)
+            = u:word() _ r:range() { (u, r) }
+
+        rule pattern() -> Regex
+            = "\"" p:$((!"\"" [_])*) "\"" {? Regex::new(p).map_err(|_| "valid regex") }
+
+        rule kind() -> FieldKind
+            = "any" { FieldKind::Any }
+            / "number" _ r:range() { FieldKind::Number(r) }
+            / "measure" _ u:unit() ++ ("," _) { FieldKind::Measure(u) }
+            / "pattern" _ p:pattern() { FieldKind::Pattern(p) }
+            / "one of" _ w:word() ++ _ { FieldKind::OneOf(w) }
+
+        rule ws() = [' ' | '\t']*
+
+        rule comment() = "#" (!"\n" [_])*
+
+        rule field() -> FieldRule
+            = name:word() ":" _ o:("optional" _)? kind:kind() {
+                FieldRule { name, required: o.is_none(), kind }
+            }
+
+        rule line() -> Option<FieldRule>
+            = ws() f:field()? ws() comment()? { f }
+
+        pub rule fields() -> Vec<FieldRule>
+            = l:line() ** "\n" { l.into_iter().flatten().collect() }
+    }
+}
+
+impl FromStr for Schema {
+    type Err = peg::error::ParseError<peg::str::LineCol>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            fields: schema::fields(s)?,
+        })
+    }
+}
+
+impl Schema {
+    pub fn load(
+        path: impl AsRef<Path>,
+    ) -> Result<Self, Either<io::Error, peg::error::ParseError<peg::str::LineCol>>> {
+        fs::read_to_string(path)
+            .map_err(Either::Left)?
+            .parse()
+            .map_err(Either::Right)
+    }
+
+    pub fn required(&self) -> impl Iterator<Item = &FieldRule> {
+        self.fields.iter().filter(|rule| rule.required)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FieldRule> {
+        self.fields.iter().find(|rule| rule.name == name)
+    }
+}
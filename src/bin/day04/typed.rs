@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum FieldError {
+    Missing(&'static str),
+    Malformed(&'static str, String),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::Missing(field) => write!(f, "missing {}", field),
+            FieldError::Malformed(field, value) => write!(f, "malformed {} {:?}", field, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Cm,
+    In,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Height {
+    pub value: u32,
+    pub unit: Unit,
+}
+
+impl Height {
+    pub fn to_unit(self, unit: Unit) -> Self {
+        let value = match (self.unit, unit) {
+            (Unit::Cm, Unit::In) => (self.value as f64 / 2.54).round() as u32,
+            (Unit::In, Unit::Cm) => (self.value as f64 * 2.54).round() as u32,
+            _ => self.value,
+        };
+        Self { value, unit }
+    }
+}
+
+impl FromStr for Height {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = if let Some(value) = s.strip_suffix("cm") {
+            (value, Unit::Cm)
+        } else if let Some(value) = s.strip_suffix("in") {
+            (value, Unit::In)
+        } else {
+            return Err(());
+        };
+        let value = value.parse().map_err(|_| ())?;
+        Ok(Self { value, unit })
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Unit::Cm => write!(f, "{}cm", self.value),
+            Unit::In => write!(f, "{}in", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl FromStr for Rgb {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').ok_or(())?;
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ());
+        Ok(Self {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EyeColor {
+    Amber,
+    Blue,
+    Brown,
+    Gray,
+    Green,
+    Hazel,
+    Other,
+}
+
+impl FromStr for EyeColor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "amb" => Ok(EyeColor::Amber),
+            "blu" => Ok(EyeColor::Blue),
+            "brn" => Ok(EyeColor::Brown),
+            "gry" => Ok(EyeColor::Gray),
+            "grn" => Ok(EyeColor::Green),
+            "hzl" => Ok(EyeColor::Hazel),
+            "oth" => Ok(EyeColor::Other),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            EyeColor::Amber => "amb",
+            EyeColor::Blue => "blu",
+            EyeColor::Brown => "brn",
+            EyeColor::Gray => "gry",
+            EyeColor::Green => "grn",
+            EyeColor::Hazel => "hzl",
+            EyeColor::Other => "oth",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+pub struct TypedPassport {
+    pub byr: u16,
+    pub iyr: u16,
+    pub eyr: u16,
+    pub hgt: Height,
+    pub hcl: Rgb,
+    pub ecl: EyeColor,
+    pub pid: String,
+    pub cid: Option<String>,
+}
+
+fn field<T: FromStr>(
+    fields: &HashMap<String, String>,
+    name: &'static str,
+) -> Result<T, FieldError> {
+    let value = fields.get(name).ok_or(FieldError::Missing(name))?;
+    value
+        .parse()
+        .map_err(|_| FieldError::Malformed(name, value.clone()))
+}
+
+impl TryFrom<&HashMap<String, String>> for TypedPassport {
+    type Error = FieldError;
+
+    fn try_from(fields: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        let pid: String = field(fields, "pid")?;
+        if pid.is_empty() || !pid.chars().all(|c| c.is_ascii_digit()) {
+            return Err(FieldError::Malformed("pid", pid));
+        }
+        Ok(Self {
+            byr: field(fields, "byr")?,
+            iyr: field(fields, "iyr")?,
+            eyr: field(fields, "eyr")?,
+            hgt: field(fields, "hgt")?,
+            hcl: field(fields, "hcl")?,
+            ecl: field(fields, "ecl")?,
+            pid,
+            cid: fields.get("cid").cloned(),
+        })
+    }
+}
+
+impl fmt::Display for TypedPassport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.byr, self.iyr, self.eyr, self.hgt, self.hcl, self.ecl, self.pid
+        )?;
+        if let Some(cid) = &self.cid {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
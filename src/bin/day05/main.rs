@@ -1,16 +1,251 @@
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy)]
+struct Layout {
+    row_bits: u32,
+    column_bits: u32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            row_bits: 7,
+            column_bits: 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum LayoutError {
+    NotANumber(String),
+    TooManyBits(u32),
+    WrongArgumentCount(usize),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::NotANumber(s) => write!(f, "{:?} is not a number", s),
+            LayoutError::TooManyBits(bits) => {
+                write!(f, "seat IDs are at most 32 bits, layout needs {}", bits)
+            }
+            LayoutError::WrongArgumentCount(count) => write!(
+                f,
+                "a layout is given as ROW_BITS COLUMN_BITS, got {} argument(s)",
+                count
+            ),
+        }
+    }
+}
+
+impl Layout {
+    fn new(row_bits: u32, column_bits: u32) -> Result<Self, LayoutError> {
+        match row_bits.checked_add(column_bits) {
+            Some(bits) if bits <= 32 => Ok(Self {
+                row_bits,
+                column_bits,
+            }),
+            _ => Err(LayoutError::TooManyBits(
+                row_bits.saturating_add(column_bits),
+            )),
+        }
+    }
+
+    // u64 so that a full 32 bit layout does not overflow
+    fn rows(&self) -> u64 {
+        1 << self.row_bits
+    }
+
+    fn columns(&self) -> u64 {
+        1 << self.column_bits
+    }
+
+    fn seat_id(&self, row: u64, column: u64) -> u32 {
+        (row << self.column_bits | column) as u32
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BoardingPass {
+    row: u32,
+    column: u32,
+    layout: Layout,
+}
+
+fn decode_bits(code: &str, zero: char, one: char) -> Option<u32> {
+    code.chars().try_fold(0, |acc, c| match c {
+        _ if c == zero => Some(acc << 1),
+        _ if c == one => Some(acc << 1 | 1),
+        _ => None,
+    })
+}
+
+impl BoardingPass {
+    fn decode(code: &str, layout: Layout) -> Option<Self> {
+        let row_len = layout.row_bits as usize;
+        if code.len() != row_len + layout.column_bits as usize {
+            return None;
+        }
+        Some(Self {
+            row: decode_bits(code.get(..row_len)?, 'F', 'B')?,
+            column: decode_bits(code.get(row_len..)?, 'L', 'R')?,
+            layout,
+        })
+    }
+
+    fn new(row: u32, column: u32, layout: Layout) -> Option<Self> {
+        if u64::from(row) >= layout.rows() || u64::from(column) >= layout.columns() {
+            return None;
+        }
+        Some(Self {
+            row,
+            column,
+            layout,
+        })
+    }
+
+    fn from_seat_id(seat_id: u32, layout: Layout) -> Option<Self> {
+        let seat_id = u64::from(seat_id);
+        Self::new(
+            u32::try_from(seat_id >> layout.column_bits).ok()?,
+            (seat_id & (layout.columns() - 1)) as u32,
+            layout,
+        )
+    }
+
+    fn seat_id(&self) -> u32 {
+        self.layout
+            .seat_id(u64::from(self.row), u64::from(self.column))
+    }
+}
+
+fn encode_bits(
+    f: &mut fmt::Formatter<'_>,
+    value: u32,
+    bits: u32,
+    zero: char,
+    one: char,
+) -> fmt::Result {
+    for bit in (0..bits).rev() {
+        write!(f, "{}", if value >> bit & 1 == 1 { one } else { zero })?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for BoardingPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        encode_bits(f, self.row, self.layout.row_bits, 'F', 'B')?;
+        encode_bits(f, self.column, self.layout.column_bits, 'L', 'R')
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Seat {
+    Occupied,
+    MissingFront,
+    MissingBack,
+    Gap,
+}
+
+struct SeatMap {
+    layout: Layout,
+    occupied: BTreeSet<u32>,
+}
+
+impl SeatMap {
+    fn new(passes: &[BoardingPass], layout: Layout) -> Self {
+        Self {
+            layout,
+            occupied: passes.iter().map(|pass| pass.seat_id()).collect(),
+        }
+    }
+
+    fn seat(&self, seat_id: u32) -> Seat {
+        if self.occupied.contains(&seat_id) {
+            Seat::Occupied
+        } else if self
+            .occupied
+            .iter()
+            .next()
+            .is_none_or(|&first| seat_id < first)
+        {
+            Seat::MissingFront
+        } else if self
+            .occupied
+            .iter()
+            .next_back()
+            .is_some_and(|&last| seat_id > last)
+        {
+            Seat::MissingBack
+        } else {
+            Seat::Gap
+        }
+    }
+
+    fn seat_ids(&self) -> RangeInclusive<u32> {
+        0..=(self.layout.rows() * self.layout.columns() - 1) as u32
+    }
+
+    fn unoccupied(&self) -> impl Iterator<Item = (u32, Seat)> + '_ {
+        self.seat_ids()
+            .map(move |seat_id| (seat_id, self.seat(seat_id)))
+            .filter(|&(_, seat)| seat != Seat::Occupied)
+    }
+
+    fn empty_rows(&self, kind: Seat) -> usize {
+        let columns = self.layout.columns();
+        (0..self.layout.rows())
+            .filter(|&row| {
+                (0..columns).all(|column| self.seat(self.layout.seat_id(row, column)) == kind)
+            })
+            .count()
+    }
+
+    fn gaps(&self) -> Vec<RangeInclusive<u32>> {
+        let mut gaps: Vec<RangeInclusive<u32>> = Vec::new();
+        for (seat_id, _) in self.unoccupied().filter(|&(_, seat)| seat == Seat::Gap) {
+            match gaps.last_mut() {
+                Some(gap) if gap.end() + 1 == seat_id => *gap = *gap.start()..=seat_id,
+                _ => gaps.push(seat_id..=seat_id),
+            }
+        }
+        gaps
+    }
+}
+
+impl fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.layout.columns();
+        for row in 0..self.layout.rows() {
+            write!(f, "{:>4} ", row)?;
+            for column in 0..columns {
+                if column == columns / 2 {
+                    write!(f, " ")?;
+                }
+                let symbol = match self.seat(self.layout.seat_id(row, column)) {
+                    Seat::Occupied => '#',
+                    Seat::Gap => 'O',
+                    Seat::MissingFront | Seat::MissingBack => '.',
+                };
+                write!(f, "{}", symbol)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
 
 lazy_static! {
-    static ref SEAT_IDS: Vec<u16> = include_str!("input.txt")
+    static ref PASSES: Vec<BoardingPass> = include_str!("input.txt")
         .lines()
-        .flat_map(|line| u16::from_str_radix(
-            &line
-                .replace(&['B', 'R'][..], "1")
-                .replace(&['F', 'L'][..], "0"),
-            2,
-        ))
+        .flat_map(|line| BoardingPass::decode(line, Layout::default()))
         .collect();
+    static ref SEAT_IDS: Vec<u32> = PASSES.iter().map(|pass| pass.seat_id()).collect();
 }
 
 fn part1() {
@@ -28,7 +263,82 @@ fn part2() {
     println!("**  {}", seat_before + 1);
 }
 
+fn seat_map() {
+    let map = SeatMap::new(&PASSES, Layout::default());
+    print!("{}", map);
+    let count = |kind| map.unoccupied().filter(|&(_, seat)| seat == kind).count();
+    println!(
+        "{} unoccupied seats: {} missing at the front ({} full rows), {} missing at the back ({} full rows), {} interior",
+        map.unoccupied().count(),
+        count(Seat::MissingFront),
+        map.empty_rows(Seat::MissingFront),
+        count(Seat::MissingBack),
+        map.empty_rows(Seat::MissingBack),
+        count(Seat::Gap),
+    );
+    for gap in map.gaps() {
+        println!("interior gap {}..={}:", gap.start(), gap.end());
+        for seat_id in gap {
+            let pass = BoardingPass::from_seat_id(seat_id, map.layout).unwrap();
+            println!(
+                "    seat ID {} (row {}, column {}, {})",
+                seat_id, pass.row, pass.column, pass
+            );
+        }
+    }
+}
+
+fn parse_number(s: &str) -> Result<u32, LayoutError> {
+    s.parse().map_err(|_| LayoutError::NotANumber(s.to_owned()))
+}
+
+fn parse_layout(args: &[String]) -> Result<Layout, LayoutError> {
+    match args {
+        [] => Ok(Layout::default()),
+        [row_bits, column_bits] => Layout::new(parse_number(row_bits)?, parse_number(column_bits)?),
+        _ => Err(LayoutError::WrongArgumentCount(args.len())),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), LayoutError> {
+    match args {
+        [cmd, code, rest @ ..] if cmd == "decode" => {
+            match BoardingPass::decode(code, parse_layout(rest)?) {
+                Some(pass) => println!(
+                    "row {}, column {}, seat ID {}",
+                    pass.row,
+                    pass.column,
+                    pass.seat_id()
+                ),
+                None => println!("invalid boarding pass {:?}", code),
+            }
+        }
+        [cmd, seat_id, rest @ ..] if cmd == "encode" => {
+            match BoardingPass::from_seat_id(parse_number(seat_id)?, parse_layout(rest)?) {
+                Some(pass) => println!("{}", pass),
+                None => println!("seat ID {} is outside the plane", seat_id),
+            }
+        }
+        [cmd, row, column, rest @ ..] if cmd == "seat" => {
+            let layout = parse_layout(rest)?;
+            match BoardingPass::new(parse_number(row)?, parse_number(column)?, layout) {
+                Some(pass) => println!("{}, seat ID {}", pass, pass.seat_id()),
+                None => println!("row {}, column {} is outside the plane", row, column),
+            }
+        }
+        [cmd] if cmd == "map" => seat_map(),
+        _ => {
+            part1();
+            part2();
+        }
+    }
+    Ok(())
+}
+
 fn main() {
-    part1();
-    part2();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(&args) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
 }
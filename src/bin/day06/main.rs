@@ -1,4 +1,7 @@
 use lazy_static::lazy_static;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 
 lazy_static! {
     static ref ANSWERS: Vec<Vec<String>> = include_str!("input.txt")
@@ -7,31 +10,365 @@ lazy_static! {
         .collect();
 }
 
-fn part1() {
-    let sum: usize = ANSWERS
-        .iter()
-        .map(|grp| {
-            ('a'..='z')
-                .filter(|&q| grp.iter().any(|answer| answer.contains(q)))
-                .count()
+struct Alphabet {
+    symbols: Vec<char>,
+}
+
+#[derive(Debug)]
+enum AlphabetError {
+    TooManySymbols(usize),
+    DuplicateSymbol(char),
+    UnknownSymbol(char),
+}
+
+impl fmt::Display for AlphabetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlphabetError::TooManySymbols(n) => {
+                write!(
+                    f,
+                    "alphabet has {} symbols, at most {} are supported",
+                    n,
+                    u32::BITS
+                )
+            }
+            AlphabetError::DuplicateSymbol(c) => write!(f, "duplicate symbol {:?} in alphabet", c),
+            AlphabetError::UnknownSymbol(c) => write!(f, "symbol {:?} is not in the alphabet", c),
+        }
+    }
+}
+
+impl Alphabet {
+    fn new(symbols: impl IntoIterator<Item = char>) -> Result<Self, AlphabetError> {
+        let symbols: Vec<char> = symbols.into_iter().collect();
+        if symbols.len() > u32::BITS as usize {
+            return Err(AlphabetError::TooManySymbols(symbols.len()));
+        }
+        for (i, &c) in symbols.iter().enumerate() {
+            if symbols[..i].contains(&c) {
+                return Err(AlphabetError::DuplicateSymbol(c));
+            }
+        }
+        Ok(Self { symbols })
+    }
+
+    fn encode(&self, answer: &str) -> Result<u32, AlphabetError> {
+        answer.chars().try_fold(0, |set, c| {
+            let bit = self
+                .symbols
+                .iter()
+                .position(|&s| s == c)
+                .ok_or(AlphabetError::UnknownSymbol(c))?;
+            Ok(set | 1 << bit)
         })
+    }
+
+    fn mask(&self) -> u32 {
+        (0..self.symbols.len()).fold(0, |set, bit| set | 1 << bit)
+    }
+
+    fn decode(&self, set: u32) -> String {
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| set & 1 << bit != 0)
+            .map(|(_, &c)| c)
+            .collect()
+    }
+}
+
+struct Group {
+    people: Vec<u32>,
+    questions: u32,
+}
+
+impl Group {
+    fn parse(answers: &[String], alphabet: &Alphabet) -> Result<Self, AlphabetError> {
+        let people = answers
+            .iter()
+            .map(|answer| alphabet.encode(answer))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            people,
+            questions: alphabet.mask(),
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.people.len()
+    }
+
+    fn histogram(&self) -> [usize; u32::BITS as usize] {
+        let mut counts = [0; u32::BITS as usize];
+        for &person in &self.people {
+            for (bit, count) in counts.iter_mut().enumerate() {
+                if person & 1 << bit != 0 {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    fn answered_by_at_least(&self, k: usize) -> u32 {
+        self.histogram()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0 && count >= k)
+            .fold(0, |set, (bit, _)| set | 1 << bit)
+    }
+
+    fn answered_by_exactly(&self, k: usize) -> u32 {
+        self.histogram()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count == k)
+            .fold(0, |set, (bit, _)| set | 1 << bit)
+    }
+
+    fn most_common(&self) -> u32 {
+        let counts = self.histogram();
+        let max = counts.iter().copied().filter(|&c| c > 0).max();
+        max.map_or(0, |max| self.answered_by_exactly(max))
+    }
+
+    fn least_common(&self) -> u32 {
+        let counts = self.histogram();
+        let min = counts.iter().copied().filter(|&c| c > 0).min();
+        min.map_or(0, |min| self.answered_by_exactly(min))
+    }
+
+    fn is_unanimous(&self) -> bool {
+        self.people.windows(2).all(|pair| pair[0] == pair[1])
+    }
+
+    fn answered_by_anyone(&self) -> u32 {
+        self.people.iter().fold(0, |set, &person| set | person)
+    }
+
+    fn answered_by_everyone(&self) -> u32 {
+        self.people
+            .iter()
+            .fold(self.questions, |set, &person| set & person)
+    }
+}
+
+fn groups(alphabet: &Alphabet) -> Result<Vec<Group>, AlphabetError> {
+    ANSWERS
+        .iter()
+        .map(|grp| Group::parse(grp, alphabet))
+        .collect()
+}
+
+fn part1(groups: &[Group]) {
+    let sum: u32 = groups
+        .iter()
+        .map(|grp| grp.answered_by_anyone().count_ones())
         .sum();
     println!("*   {}", sum);
 }
 
-fn part2() {
-    let sum: usize = ANSWERS
+fn part2(groups: &[Group]) {
+    let sum: u32 = groups
         .iter()
-        .map(|grp| {
-            ('a'..='z')
-                .filter(|&q| grp.iter().all(|answer| answer.contains(q)))
-                .count()
-        })
+        .map(|grp| grp.answered_by_everyone().count_ones())
         .sum();
     println!("**  {}", sum);
 }
 
+fn at_least(groups: &[Group], k: usize) {
+    let sum: u32 = groups
+        .iter()
+        .map(|grp| grp.answered_by_at_least(k).count_ones())
+        .sum();
+    println!("questions answered by at least {} people: {}", k, sum);
+}
+
+fn histogram(groups: &[Group], alphabet: &Alphabet, group: usize) {
+    let grp = &groups[group - 1];
+    println!(
+        "group {}: {} people, {:?} answered by all",
+        group,
+        grp.size(),
+        alphabet.decode(grp.answered_by_everyone())
+    );
+    for (&symbol, count) in alphabet.symbols.iter().zip(grp.histogram().iter()) {
+        println!("{} {:>3}", symbol, count);
+    }
+}
+
+fn question_totals(groups: &[Group]) -> Vec<(usize, usize)> {
+    let mut totals = vec![(0, 0); u32::BITS as usize];
+    for grp in groups {
+        for (total, &count) in totals.iter_mut().zip(grp.histogram().iter()) {
+            total.0 += count;
+            if count > 0 {
+                total.1 += 1;
+            }
+        }
+    }
+    totals
+}
+
+fn stats(groups: &[Group], alphabet: &Alphabet) {
+    let people: usize = groups.iter().map(|grp| grp.size()).sum();
+    println!("{} groups, {} people", groups.len(), people);
+    println!(
+        "group size: min {}, max {}, mean {:.2}",
+        groups.iter().map(|grp| grp.size()).min().unwrap_or(0),
+        groups.iter().map(|grp| grp.size()).max().unwrap_or(0),
+        people as f64 / groups.len() as f64
+    );
+    println!(
+        "{} groups gave unanimous answers",
+        groups.iter().filter(|grp| grp.is_unanimous()).count()
+    );
+    println!("question  people  groups");
+    for (symbol, (people, groups)) in alphabet.symbols.iter().zip(question_totals(groups)) {
+        println!("{:>8}  {:>6}  {:>6}", symbol, people, groups);
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn write_group_csv(out: &mut impl Write, groups: &[Group], alphabet: &Alphabet) -> io::Result<()> {
+    writeln!(
+        out,
+        "group,size,anyone,everyone,most_common,least_common,unanimous"
+    )?;
+    for (i, grp) in groups.iter().enumerate() {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            i + 1,
+            grp.size(),
+            csv_field(&alphabet.decode(grp.answered_by_anyone())),
+            csv_field(&alphabet.decode(grp.answered_by_everyone())),
+            csv_field(&alphabet.decode(grp.most_common())),
+            csv_field(&alphabet.decode(grp.least_common())),
+            grp.is_unanimous()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_question_csv(
+    out: &mut impl Write,
+    groups: &[Group],
+    alphabet: &Alphabet,
+) -> io::Result<()> {
+    writeln!(out, "question,people,groups")?;
+    for (symbol, (people, groups)) in alphabet.symbols.iter().zip(question_totals(groups)) {
+        writeln!(
+            out,
+            "{},{},{}",
+            csv_field(&symbol.to_string()),
+            people,
+            groups
+        )?;
+    }
+    Ok(())
+}
+
+fn export(
+    groups: &[Group],
+    alphabet: &Alphabet,
+    group_path: &str,
+    question_path: Option<&str>,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(group_path)?);
+    write_group_csv(&mut out, groups, alphabet)?;
+    out.flush()?;
+    if let Some(path) = question_path {
+        let mut out = BufWriter::new(File::create(path)?);
+        write_question_csv(&mut out, groups, alphabet)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+enum Error {
+    Alphabet(AlphabetError),
+    Io(io::Error),
+    Usage(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<AlphabetError> for Error {
+    fn from(err: AlphabetError) -> Self {
+        Error::Alphabet(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Alphabet(err) => write!(f, "{}", err),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Usage(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, Error> {
+    s.parse()
+        .map_err(|_| Error::Usage(format!("{:?} is not a count", s)))
+}
+
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let alphabet = match args.iter().position(|arg| arg == "--alphabet") {
+        Some(i) if i + 1 < args.len() => {
+            let symbols = args.remove(i + 1);
+            args.remove(i);
+            Alphabet::new(symbols.chars())?
+        }
+        Some(_) => return Err(Error::Usage("--alphabet expects symbols".to_owned())),
+        None => Alphabet::new('a'..='z')?,
+    };
+    let groups = groups(&alphabet)?;
+    match args.as_slice() {
+        [cmd, k] if cmd == "at-least" => at_least(&groups, parse_count(k)?),
+        [cmd, group] if cmd == "histogram" => match parse_count(group)? {
+            group if (1..=groups.len()).contains(&group) => histogram(&groups, &alphabet, group),
+            group => {
+                return Err(Error::Usage(format!(
+                    "group {} does not exist, there are {} groups",
+                    group,
+                    groups.len()
+                )))
+            }
+        },
+        [cmd] if cmd == "stats" => stats(&groups, &alphabet),
+        [cmd, group_path, rest @ ..] if cmd == "export" => export(
+            &groups,
+            &alphabet,
+            group_path,
+            rest.first().map(String::as_str),
+        )?,
+        _ => {
+            part1(&groups);
+            part2(&groups);
+        }
+    }
+    Ok(())
+}
+
 fn main() {
-    part1();
-    part2();
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
 }
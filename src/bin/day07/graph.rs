@@ -0,0 +1,307 @@
+use itertools::Itertools;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, Write};
+
+pub type Bag = usize;
+pub type Rules = HashMap<String, Vec<(usize, String)>>;
+
+pub enum RuleError {
+    Undefined {
+        bag: String,
+        referenced_by: Vec<String>,
+    },
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Undefined { bag, referenced_by } => write!(
+                f,
+                "{} bags are referenced by {} but never defined",
+                bag,
+                referenced_by.join(", ")
+            ),
+            RuleError::Cycle(path) => write!(f, "cycle: {}", path.join(" -> ")),
+        }
+    }
+}
+
+impl RuleError {
+    fn bag(&self) -> &str {
+        match self {
+            RuleError::Undefined { bag, .. } => bag,
+            RuleError::Cycle(path) => &path[0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    Active,
+    Done,
+}
+
+pub struct BagGraph {
+    names: Vec<String>,
+    ids: HashMap<String, Bag>,
+    contents: Vec<Vec<(usize, Bag)>>,
+    containers: Vec<Vec<Bag>>,
+    defined: Vec<bool>,
+}
+
+impl BagGraph {
+    pub fn new(rules: &Rules) -> Self {
+        let mut graph = Self {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            contents: Vec::new(),
+            containers: Vec::new(),
+            defined: Vec::new(),
+        };
+        for (outer, inner) in rules {
+            let outer = graph.intern(outer);
+            graph.defined[outer] = true;
+            for (count, name) in inner {
+                let bag = graph.intern(name);
+                graph.contents[outer].push((*count, bag));
+                graph.containers[bag].push(outer);
+            }
+        }
+        graph
+    }
+
+    fn intern(&mut self, name: &str) -> Bag {
+        if let Some(&bag) = self.ids.get(name) {
+            return bag;
+        }
+        let bag = self.names.len();
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), bag);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        self.defined.push(false);
+        bag
+    }
+
+    pub fn id(&self, name: &str) -> Option<Bag> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn name(&self, bag: Bag) -> &str {
+        &self.names[bag]
+    }
+
+    pub fn contents(&self, bag: Bag) -> &[(usize, Bag)] {
+        &self.contents[bag]
+    }
+
+    pub fn containers(&self, bag: Bag) -> &[Bag] {
+        &self.containers[bag]
+    }
+
+    pub fn reachable<I>(&self, from: Bag, edges: impl Fn(Bag) -> I) -> Vec<bool>
+    where
+        I: IntoIterator<Item = Bag>,
+    {
+        let mut seen = vec![false; self.len()];
+        let mut queue: VecDeque<Bag> = edges(from).into_iter().collect();
+        while let Some(bag) = queue.pop_front() {
+            if !seen[bag] {
+                seen[bag] = true;
+                queue.extend(edges(bag));
+            }
+        }
+        seen
+    }
+
+    pub fn eventual_containers(&self, bag: Bag) -> Vec<Bag> {
+        let seen = self.reachable(bag, |bag| self.containers(bag).iter().copied());
+        (0..self.len()).filter(|&bag| seen[bag]).collect()
+    }
+
+    pub fn upstream(&self, bag: Bag) -> Vec<bool> {
+        let mut seen = self.reachable(bag, |bag| self.containers(bag).iter().copied());
+        seen[bag] = true;
+        seen
+    }
+
+    pub fn downstream(&self, bag: Bag) -> Vec<bool> {
+        let mut seen = self.reachable(bag, |bag| self.contents(bag).iter().map(|&(_, b)| b));
+        seen[bag] = true;
+        seen
+    }
+
+    pub fn write_dot(&self, out: &mut impl Write, include: &[bool]) -> io::Result<()> {
+        let bags: Vec<Bag> = (0..self.len())
+            .filter(|&bag| include[bag])
+            .sorted_by_key(|&bag| self.name(bag))
+            .collect();
+        writeln!(out, "digraph bags {{")?;
+        for &bag in &bags {
+            writeln!(out, "    {:?};", self.name(bag))?;
+        }
+        for &outer in &bags {
+            for &(count, inner) in self.contents(outer) {
+                if include[inner] {
+                    writeln!(
+                        out,
+                        "    {:?} -> {:?} [label={}];",
+                        self.name(outer),
+                        self.name(inner),
+                        count
+                    )?;
+                }
+            }
+        }
+        writeln!(out, "}}")
+    }
+
+    pub fn topological_order(&self) -> Option<Vec<Bag>> {
+        let mut pending: Vec<usize> = self.contents.iter().map(|inner| inner.len()).collect();
+        let mut queue: VecDeque<Bag> = (0..self.len()).filter(|&bag| pending[bag] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(bag) = queue.pop_front() {
+            order.push(bag);
+            for &outer in self.containers(bag) {
+                pending[outer] -= 1;
+                if pending[outer] == 0 {
+                    queue.push_back(outer);
+                }
+            }
+        }
+        if order.len() == self.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    pub fn total_contents(&self) -> Option<Vec<usize>> {
+        let mut totals = vec![0; self.len()];
+        for bag in self.topological_order()? {
+            totals[bag] = self
+                .contents(bag)
+                .iter()
+                .map(|&(count, inner)| count * (1 + totals[inner]))
+                .sum();
+        }
+        Some(totals)
+    }
+
+    pub fn paths(&self, from: Bag, to: Bag) -> Vec<Vec<(usize, Bag)>> {
+        let leads_to = self.upstream(to);
+        let mut paths = Vec::new();
+        if leads_to[from] {
+            self.extend_paths(&mut vec![(1, from)], to, &leads_to, &mut paths);
+        }
+        paths
+    }
+
+    fn extend_paths(
+        &self,
+        path: &mut Vec<(usize, Bag)>,
+        to: Bag,
+        leads_to: &[bool],
+        paths: &mut Vec<Vec<(usize, Bag)>>,
+    ) {
+        let (_, bag) = *path.last().unwrap();
+        if bag == to {
+            paths.push(path.clone());
+            return;
+        }
+        for &(count, inner) in self.contents(bag) {
+            if leads_to[inner] {
+                path.push((count, inner));
+                self.extend_paths(path, to, leads_to, paths);
+                path.pop();
+            }
+        }
+    }
+
+    pub fn bill_of_materials(&self, bag: Bag) -> Option<Vec<(Bag, usize)>> {
+        let mut multiplicity = vec![0; self.len()];
+        multiplicity[bag] = 1;
+        for outer in self.topological_order()?.into_iter().rev() {
+            for &(count, inner) in self.contents(outer) {
+                multiplicity[inner] += multiplicity[outer] * count;
+            }
+        }
+        Some(
+            (0..self.len())
+                .filter(|&b| b != bag && multiplicity[b] > 0)
+                .map(|b| (b, multiplicity[b]))
+                .collect(),
+        )
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<RuleError>> {
+        // ids follow interning order, so walk bags by name to keep the
+        // reported cycles stable between runs
+        let by_name: Vec<Bag> = (0..self.len())
+            .sorted_by_key(|&bag| self.name(bag))
+            .collect();
+        let mut errors: Vec<RuleError> = by_name
+            .iter()
+            .copied()
+            .filter(|&bag| !self.defined[bag])
+            .map(|bag| RuleError::Undefined {
+                bag: self.name(bag).to_owned(),
+                referenced_by: self
+                    .containers(bag)
+                    .iter()
+                    .map(|&outer| self.name(outer).to_owned())
+                    .sorted()
+                    .dedup()
+                    .collect(),
+            })
+            .collect();
+        let mut visits = vec![Visit::New; self.len()];
+        let mut path = Vec::new();
+        for &bag in &by_name {
+            self.find_cycles(bag, &mut visits, &mut path, &mut errors);
+        }
+        errors.sort_by(|a, b| a.bag().cmp(b.bag()));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn find_cycles(
+        &self,
+        bag: Bag,
+        visits: &mut [Visit],
+        path: &mut Vec<Bag>,
+        errors: &mut Vec<RuleError>,
+    ) {
+        match visits[bag] {
+            Visit::Done => return,
+            Visit::Active => {
+                let start = path.iter().position(|&b| b == bag).unwrap();
+                let cycle = path[start..]
+                    .iter()
+                    .chain(Some(&bag))
+                    .map(|&b| self.name(b).to_owned())
+                    .collect();
+                errors.push(RuleError::Cycle(cycle));
+                return;
+            }
+            Visit::New => {}
+        }
+        visits[bag] = Visit::Active;
+        path.push(bag);
+        for &(_, inner) in self.contents(bag) {
+            self.find_cycles(inner, visits, path, errors);
+        }
+        path.pop();
+        visits[bag] = Visit::Done;
+    }
+}
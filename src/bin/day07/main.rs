@@ -1,55 +1,166 @@
-#![feature(str_split_once)]
-use lazy_static::lazy_static;
+use itertools::Itertools;
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::process;
 
-lazy_static! {
-    static ref RULES: HashMap<String, Vec<(usize, String)>> = include_str!("input.txt")
-        .lines()
-        .map(|line| {
-            let (key, values) = line.split_once(" bags contain ").unwrap();
-            if values == "no other bags." {
-                return (key.to_owned(), Vec::new());
+mod graph;
+
+use graph::{Bag, BagGraph, Rules};
+
+struct MalformedRule {
+    line: usize,
+    text: String,
+}
+
+impl fmt::Display for MalformedRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: cannot parse rule {:?}", self.line, self.text)
+    }
+}
+
+fn parse_rule(line: &str) -> Option<(String, Vec<(usize, String)>)> {
+    let (key, values) = line.split_once(" bags contain ")?;
+    let values = values.strip_suffix('.')?;
+    if values == "no other bags" {
+        return Some((key.to_owned(), Vec::new()));
+    }
+    let values = values
+        .split(", ")
+        .map(|s| {
+            let (s, bags) = s.rsplit_once(' ')?;
+            let (amount, name) = s.split_once(' ')?;
+            match bags {
+                "bag" | "bags" => Some((amount.parse().ok()?, name.to_owned())),
+                _ => None,
             }
-            let values = values
-                .split(", ")
-                .map(|s| {
-                    let (s, _) = s.rsplit_once(' ').unwrap();
-                    let (amount, name) = s.split_once(' ').unwrap();
-                    (amount.parse().unwrap(), name.to_owned())
-                })
-                .collect();
-            (key.to_owned(), values)
         })
-        .collect();
+        .collect::<Option<_>>()?;
+    Some((key.to_owned(), values))
 }
 
-fn can_contain_eventually(outer: &str, inner: &str) -> bool {
-    RULES[outer]
-        .iter()
-        .any(|(_, bag)| bag == inner || can_contain_eventually(bag, inner))
+fn parse_rules(input: &str) -> Result<Rules, Vec<MalformedRule>> {
+    let mut rules = HashMap::new();
+    let mut errors = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_rule(line) {
+            Some((key, values)) => {
+                rules.insert(key, values);
+            }
+            None => errors.push(MalformedRule {
+                line: i + 1,
+                text: line.to_owned(),
+            }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(rules)
+    } else {
+        Err(errors)
+    }
+}
+
+fn exit_with_errors(errors: &[impl fmt::Display]) -> ! {
+    for error in errors {
+        eprintln!("error: {}", error);
+    }
+    process::exit(1);
 }
 
-fn part1() {
-    let count = RULES
-        .keys()
-        .filter(|bag| can_contain_eventually(bag, "shiny gold"))
-        .count();
+fn part1(graph: &BagGraph) {
+    let shiny_gold = graph.id("shiny gold").unwrap();
+    let count = graph.eventual_containers(shiny_gold).len();
     println!("*   {}", count);
 }
 
-fn count_inner_bags(outer: &str) -> usize {
-    RULES[outer]
+fn part2(graph: &BagGraph) {
+    let shiny_gold = graph.id("shiny gold").unwrap();
+    let count = graph.total_contents().unwrap()[shiny_gold];
+    println!("**  {}", count);
+}
+
+fn bag(graph: &BagGraph, name: &str) -> Result<Bag, String> {
+    graph
+        .id(name)
+        .ok_or_else(|| format!("unknown bag {:?}", name))
+}
+
+fn dot(graph: &BagGraph, args: &[String]) -> Result<(), String> {
+    let include = match args {
+        [flag, name] if flag == "--from" => graph.downstream(bag(graph, name)?),
+        [flag, name] if flag == "--to" => graph.upstream(bag(graph, name)?),
+        _ => vec![true; graph.len()],
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    graph
+        .write_dot(&mut out, &include)
+        .and_then(|()| out.flush())
+        .map_err(|err| err.to_string())
+}
+
+fn paths(graph: &BagGraph, args: &[String]) -> Result<(), String> {
+    let to = bag(graph, args.first().map_or("shiny gold", String::as_str))?;
+    let outers = match args.get(1) {
+        Some(name) => vec![bag(graph, name)?],
+        None => (0..graph.len())
+            .filter(|&b| b != to && graph.containers(b).is_empty())
+            .collect(),
+    };
+    for from in outers {
+        for path in graph.paths(from, to) {
+            let steps = path
+                .iter()
+                .map(|&(count, b)| format!("{} {}", count, graph.name(b)))
+                .join(" > ");
+            println!("{}", steps);
+        }
+    }
+    Ok(())
+}
+
+fn bill_of_materials(graph: &BagGraph, name: &str) -> Result<(), String> {
+    let bom = graph.bill_of_materials(bag(graph, name)?).unwrap();
+    for (inner, count) in bom
         .iter()
-        .map(|(count, bag)| count * (1 + count_inner_bags(bag)))
-        .sum()
+        .sorted_by_key(|&&(b, count)| (Reverse(count), graph.name(b)))
+    {
+        println!("{:>6}  {}", count, graph.name(*inner));
+    }
+    println!(
+        "{:>6}  total",
+        bom.iter().map(|&(_, count)| count).sum::<usize>()
+    );
+    Ok(())
 }
 
-fn part2() {
-    let count = count_inner_bags("shiny gold");
-    println!("**  {}", count);
+fn run(graph: &BagGraph, args: &[String]) -> Result<(), String> {
+    match args.split_first() {
+        Some((cmd, rest)) if cmd == "dot" => dot(graph, rest),
+        Some((cmd, rest)) if cmd == "paths" => paths(graph, rest),
+        Some((cmd, [name])) if cmd == "bom" => bill_of_materials(graph, name),
+        Some((cmd, _)) if cmd == "bom" => Err("bom expects exactly one bag name".to_owned()),
+        _ => {
+            part1(graph);
+            part2(graph);
+            Ok(())
+        }
+    }
 }
 
 fn main() {
-    part1();
-    part2();
+    let rules =
+        parse_rules(include_str!("input.txt")).unwrap_or_else(|errors| exit_with_errors(&errors));
+    let graph = BagGraph::new(&rules);
+    if let Err(errors) = graph.validate() {
+        exit_with_errors(&errors);
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(&graph, &args) {
+        exit_with_errors(&[err]);
+    }
 }
@@ -0,0 +1,150 @@
+use crate::vm::Inst;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AsmErrorKind {
+    BadInstruction(String),
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+}
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::BadInstruction(s) => write!(f, "bad instruction {:?}", s),
+            AsmErrorKind::DuplicateLabel(s) => write!(f, "duplicate label {:?}", s),
+            AsmErrorKind::UndefinedLabel(s) => write!(f, "undefined label {:?}", s),
+        }
+    }
+}
+
+enum Operand<'a> {
+    Offset(isize),
+    Label(&'a str),
+}
+
+fn is_label(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split([';', '#']).next().unwrap().trim()
+}
+
+pub fn assemble(source: &str) -> Result<Vec<Inst>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let mut rest = strip_comment(line);
+        if let Some((label, after)) = rest.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(AsmError {
+                    line: line_no,
+                    kind: AsmErrorKind::BadInstruction(line.to_owned()),
+                });
+            }
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(AsmError {
+                    line: line_no,
+                    kind: AsmErrorKind::DuplicateLabel(label.to_owned()),
+                });
+            }
+            rest = after.trim();
+        }
+        if !rest.is_empty() {
+            statements.push((line_no, rest));
+        }
+    }
+    statements
+        .iter()
+        .enumerate()
+        .map(|(pc, &(line, statement))| {
+            let bad = || AsmError {
+                line,
+                kind: AsmErrorKind::BadInstruction(statement.to_owned()),
+            };
+            let mut words = statement.split_whitespace();
+            let op = words.next().ok_or_else(bad)?;
+            let operand = words.next();
+            if words.next().is_some() {
+                return Err(bad());
+            }
+            match (op, operand) {
+                ("hlt", None) => return Ok(Inst::Hlt),
+                // disassembled from a line that did not parse
+                ("???", None) => return Ok(Inst::Invalid),
+                _ => {}
+            }
+            let operand = match operand.ok_or_else(bad)? {
+                s if is_label(s) => Operand::Label(s),
+                s => Operand::Offset(s.parse().map_err(|_| bad())?),
+            };
+            let arg = match operand {
+                Operand::Offset(offset) => offset,
+                Operand::Label(label) if matches!(op, "jmp" | "nop" | "jz" | "jnz") => {
+                    let target = labels.get(label).ok_or_else(|| AsmError {
+                        line,
+                        kind: AsmErrorKind::UndefinedLabel(label.to_owned()),
+                    })?;
+                    *target as isize - pc as isize
+                }
+                Operand::Label(_) => return Err(bad()),
+            };
+            format!("{} {:+}", op, arg).parse().map_err(|_| bad())
+        })
+        .collect()
+}
+
+fn jump_offset(inst: Inst) -> Option<isize> {
+    match inst {
+        Inst::Jmp(arg) | Inst::Jz(arg) | Inst::Jnz(arg) => Some(arg),
+        _ => None,
+    }
+}
+
+pub fn disassemble(program: &[Inst]) -> String {
+    let len = program.len() as isize;
+    let target = |pc: usize, inst: Inst| {
+        jump_offset(inst)
+            .and_then(|offset| (pc as isize).checked_add(offset))
+            .filter(|target| (0..=len).contains(target))
+    };
+    let targets: BTreeSet<isize> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(pc, &inst)| target(pc, inst))
+        .collect();
+    let mut out = String::new();
+    for (pc, &inst) in program.iter().enumerate() {
+        let label = if targets.contains(&(pc as isize)) {
+            format!("L{}:", pc)
+        } else {
+            String::new()
+        };
+        let text = match (target(pc, inst), inst.to_string().split_once(' ')) {
+            (Some(target), Some((op, _))) => format!("{} L{}", op, target),
+            _ => inst.to_string(),
+        };
+        let comment = match target(pc, inst) {
+            Some(target) => format!("; {} -> {}", pc, target),
+            None => format!("; {}", pc),
+        };
+        out += format!("{:<8}{:<16}{}", label, text, comment).trim_end();
+        out.push('\n');
+    }
+    if targets.contains(&len) {
+        out += &format!("L{}:\n", len);
+    }
+    out
+}
@@ -0,0 +1,211 @@
+use crate::vm::{self, Inst, Registers, Status, Vm};
+use std::collections::{BTreeSet, HashSet};
+use std::io::{self, BufRead, Write};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Watch {
+    Change,
+    Equals(isize),
+}
+
+enum Stop {
+    Breakpoint,
+    Watchpoint(isize),
+    Looped,
+    Halted,
+    Fault(String),
+}
+
+pub struct Debugger<'p> {
+    program: &'p [Inst],
+    vm: Vm,
+    history: Vec<Registers>,
+    breakpoints: BTreeSet<isize>,
+    watches: Vec<Watch>,
+}
+
+const HELP: &str = "\
+commands:
+    s, step [N]      execute N instructions (default 1)
+    c, continue      run until a breakpoint, watchpoint, loop, halt or fault
+    b, break PC      set a breakpoint at PC
+    d, delete PC     remove the breakpoint at PC
+    w, watch [N]     stop when acc changes, or when acc equals N
+    unwatch          remove all watchpoints
+    r, back [N]      rewind N instructions (default 1)
+    l, list [N]      list N instructions around pc (default 5)
+    p, print         print the current state
+    q, quit          exit the debugger";
+
+impl<'p> Debugger<'p> {
+    pub fn new(program: &'p [Inst]) -> Self {
+        Self {
+            program,
+            vm: Vm::new(),
+            history: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+        }
+    }
+
+    fn print_state(&self) {
+        let regs = self.vm.regs;
+        match self.vm.fetch(self.program) {
+            Some(inst) => println!(
+                "{:>5}: {:<10} acc = {}",
+                regs.pc,
+                inst.to_string(),
+                regs.acc
+            ),
+            None => println!("{:>5}: <end>      acc = {}", regs.pc, regs.acc),
+        }
+    }
+
+    fn list(&self, context: usize) {
+        let pc = self.vm.regs.pc;
+        let context = context.min(self.program.len()) as isize;
+        for addr in (pc - context).max(0)..=(pc + context) {
+            if let Some(inst) = self.program.get(addr as usize) {
+                let marker = if addr == pc { "=>" } else { "  " };
+                let bp = if self.breakpoints.contains(&addr) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{}{}{:>5}: {}", marker, bp, addr, inst);
+            }
+        }
+    }
+
+    fn step(&mut self) -> Option<Stop> {
+        let before = self.vm.regs;
+        let result = self.vm.step(self.program);
+        if self.vm.regs != before {
+            self.history.push(before);
+        }
+        match result {
+            Ok(Status::Running) => None,
+            Ok(Status::Halted) => Some(Stop::Halted),
+            Err(fault) => Some(Stop::Fault(fault.to_string())),
+        }
+    }
+
+    fn check_watches(&self, before: isize) -> Option<Stop> {
+        let acc = self.vm.regs.acc;
+        self.watches
+            .iter()
+            .any(|watch| match *watch {
+                Watch::Change => acc != before,
+                Watch::Equals(n) => acc == n && acc != before,
+            })
+            .then_some(Stop::Watchpoint(before))
+    }
+
+    fn run(&mut self, limit: Option<usize>) -> Option<Stop> {
+        let acc_affects_control_flow = vm::first_conditional_jump(self.program).is_some();
+        let mut seen = HashSet::new();
+        let mut steps = 0;
+        loop {
+            if limit.is_some_and(|limit| steps == limit) {
+                return None;
+            }
+            if steps > 0 && self.breakpoints.contains(&self.vm.regs.pc) {
+                return Some(Stop::Breakpoint);
+            }
+            let state = self.vm.regs.loop_state(acc_affects_control_flow);
+            if limit.is_none() && !seen.insert(state) {
+                return Some(Stop::Looped);
+            }
+            let acc = self.vm.regs.acc;
+            if let Some(stop) = self.step().or_else(|| self.check_watches(acc)) {
+                return Some(stop);
+            }
+            steps += 1;
+        }
+    }
+
+    fn report(&self, stop: Option<Stop>) {
+        match stop {
+            Some(Stop::Breakpoint) => println!("breakpoint at {}", self.vm.regs.pc),
+            Some(Stop::Watchpoint(before)) => {
+                println!("watchpoint: acc {} -> {}", before, self.vm.regs.acc)
+            }
+            Some(Stop::Looped) => println!("loop detected at {}", self.vm.regs.pc),
+            Some(Stop::Halted) => println!("program halted"),
+            Some(Stop::Fault(fault)) => println!("fault: {}", fault),
+            None => {}
+        }
+        self.print_state();
+    }
+
+    fn rewind(&mut self, n: usize) {
+        for _ in 0..n {
+            match self.history.pop() {
+                Some(regs) => self.vm.regs = regs,
+                None => {
+                    println!("at start of history");
+                    break;
+                }
+            }
+        }
+        self.print_state();
+    }
+
+    pub fn repl(&mut self, input: impl BufRead) -> io::Result<()> {
+        self.print_state();
+        print!("(dbg) ");
+        io::stdout().flush()?;
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let arg = |default: usize| words.get(1).map_or(Some(default), |w| w.parse().ok());
+            match words.as_slice() {
+                [] => {}
+                ["s"] | ["step"] | ["s", _] | ["step", _] => match arg(1) {
+                    Some(n) => {
+                        let stop = self.run(Some(n));
+                        self.report(stop);
+                    }
+                    None => println!("invalid count"),
+                },
+                ["c"] | ["continue"] => {
+                    let stop = self.run(None);
+                    self.report(stop);
+                }
+                ["b", pc] | ["break", pc] => match pc.parse() {
+                    Ok(pc) => {
+                        self.breakpoints.insert(pc);
+                    }
+                    Err(_) => println!("invalid address"),
+                },
+                ["d", pc] | ["delete", pc] => match pc.parse() {
+                    Ok(pc) => {
+                        self.breakpoints.remove(&pc);
+                    }
+                    Err(_) => println!("invalid address"),
+                },
+                ["w"] | ["watch"] => self.watches.push(Watch::Change),
+                ["w", n] | ["watch", n] => match n.parse() {
+                    Ok(n) => self.watches.push(Watch::Equals(n)),
+                    Err(_) => println!("invalid value"),
+                },
+                ["unwatch"] => self.watches.clear(),
+                ["r"] | ["back"] | ["r", _] | ["back", _] => match arg(1) {
+                    Some(n) => self.rewind(n),
+                    None => println!("invalid count"),
+                },
+                ["l"] | ["list"] | ["l", _] | ["list", _] => match arg(5) {
+                    Some(n) => self.list(n),
+                    None => println!("invalid count"),
+                },
+                ["p"] | ["print"] => self.print_state(),
+                ["q"] | ["quit"] => return Ok(()),
+                _ => println!("{}", HELP),
+            }
+            print!("(dbg) ");
+            io::stdout().flush()?;
+        }
+        println!();
+        Ok(())
+    }
+}
@@ -1,111 +1,278 @@
-#![feature(str_split_once)]
-use either::Either;
 use lazy_static::lazy_static;
-use std::collections::BTreeSet;
-use std::str::FromStr;
-
-#[derive(Clone, Copy)]
-enum Inst {
-    Acc(isize),
-    Jmp(isize),
-    Nop(isize),
-}
-
-#[derive(Debug)]
-struct UnrecognizedInstError;
-
-impl FromStr for Inst {
-    type Err = Either<UnrecognizedInstError, std::num::ParseIntError>;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (inst, arg) = s
-            .split_once(' ')
-            .ok_or(Either::Left(UnrecognizedInstError))?;
-        let arg = arg.parse().map_err(Either::Right)?;
-        match inst {
-            "acc" => Ok(Inst::Acc(arg)),
-            "jmp" => Ok(Inst::Jmp(arg)),
-            "nop" => Ok(Inst::Nop(arg)),
-            _ => Err(Either::Left(UnrecognizedInstError)),
-        }
-    }
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::process;
+
+mod asm;
+mod debugger;
+mod optimize;
+mod repair;
+mod trace;
+mod vm;
+
+use debugger::Debugger;
+use trace::Trace;
+use vm::{Fault, Inst, Status, Vm};
+
+lazy_static! {
+    static ref PROGRAM: Vec<Inst> = vm::parse_program(include_str!("input.txt"));
 }
 
-struct Emulator {
-    acc: isize,
-    pc: isize,
+const DEFAULT_STEP_BUDGET: usize = 1_000_000;
+
+enum RunResult {
+    Looped(isize),
+    Terminated(isize),
+    OutOfBounds { pc: isize, target: isize },
+    BudgetExceeded(isize),
+    Faulted(Fault),
 }
 
-impl Emulator {
-    fn init() -> Self {
-        Self { acc: 0, pc: 0 }
+impl fmt::Display for RunResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunResult::Looped(acc) => write!(f, "looped with acc {}", acc),
+            RunResult::Terminated(acc) => write!(f, "terminated with acc {}", acc),
+            RunResult::OutOfBounds { pc, target } => {
+                write!(f, "jumped out of bounds from {} to {}", pc, target)
+            }
+            RunResult::BudgetExceeded(acc) => write!(f, "step budget exceeded with acc {}", acc),
+            RunResult::Faulted(fault) => write!(f, "fault: {}", fault),
+        }
     }
+}
 
-    fn step(&mut self, program: &[Inst]) {
-        match program[self.pc as usize] {
-            Inst::Acc(arg) => {
-                self.acc += arg;
-                self.pc += 1;
-            }
-            Inst::Jmp(arg) => {
-                self.pc += arg;
-            }
-            Inst::Nop(_) => {
-                self.pc += 1;
+impl RunResult {
+    // addresses move when the optimiser removes instructions, so faults and
+    // out of bounds jumps are compared by kind; the step budget is spent at
+    // a different point and only has to run out in both
+    fn same_outcome(&self, other: &RunResult) -> bool {
+        match (self, other) {
+            (RunResult::Looped(a), RunResult::Looped(b))
+            | (RunResult::Terminated(a), RunResult::Terminated(b)) => a == b,
+            (RunResult::OutOfBounds { .. }, RunResult::OutOfBounds { .. })
+            | (RunResult::BudgetExceeded(_), RunResult::BudgetExceeded(_)) => true,
+            (RunResult::Faulted(a), RunResult::Faulted(b)) => {
+                mem::discriminant(a) == mem::discriminant(b)
             }
+            _ => false,
         }
     }
+}
 
-    fn should_terminate(&self, program: &[Inst]) -> bool {
-        self.pc as usize == program.len()
+fn run_until_loop_or_termination(
+    program: &[Inst],
+    budget: usize,
+    trace: Option<&mut Trace>,
+) -> RunResult {
+    // without conditional jumps acc never affects control flow, so revisiting
+    // a pc is already a loop; otherwise the whole machine state must repeat
+    let acc_affects_control_flow = vm::first_conditional_jump(program).is_some();
+    execute(program, budget, trace, acc_affects_control_flow)
+}
+
+fn execute(
+    program: &[Inst],
+    budget: usize,
+    mut trace: Option<&mut Trace>,
+    acc_affects_control_flow: bool,
+) -> RunResult {
+    let mut states_seen = HashSet::new();
+    let mut vm = Vm::new();
+    for _ in 0..budget {
+        let state = vm.regs.loop_state(acc_affects_control_flow);
+        if !states_seen.insert(state) {
+            return RunResult::Looped(vm.regs.acc);
+        }
+        let before = vm.regs;
+        let inst = vm.fetch(program);
+        let result = vm.step(program);
+        if let (Some(trace), Some(inst)) = (trace.as_mut(), inst) {
+            trace.record(before, inst, vm.regs);
+        }
+        match result {
+            Ok(Status::Running) => {}
+            Ok(Status::Halted) => return RunResult::Terminated(vm.regs.acc),
+            Err(Fault::OutOfBounds { pc, target }) => return RunResult::OutOfBounds { pc, target },
+            Err(fault) => return RunResult::Faulted(fault),
+        }
     }
+    RunResult::BudgetExceeded(vm.regs.acc)
 }
 
-lazy_static! {
-    static ref PROGRAM: Vec<Inst> = include_str!("input.txt")
-        .lines()
-        .flat_map(|line| line.parse())
-        .collect();
+fn part1() {
+    if let RunResult::Looped(res) =
+        run_until_loop_or_termination(&PROGRAM, DEFAULT_STEP_BUDGET, None)
+    {
+        println!("*   {}", res);
+    }
 }
 
-enum RunResult {
-    Looped(isize),
-    Terminated(isize),
+fn part2() {
+    for fix in repair::find_fixes(&PROGRAM).unwrap() {
+        if let RunResult::Terminated(res) =
+            run_until_loop_or_termination(&fix.apply(&PROGRAM), DEFAULT_STEP_BUDGET, None)
+        {
+            println!("**  {}", res);
+        }
+    }
+}
+
+fn run(path: &str, budget: usize) -> io::Result<()> {
+    let program = vm::parse_program(&fs::read_to_string(path)?);
+    println!("{}", run_until_loop_or_termination(&program, budget, None));
+    Ok(())
 }
 
-fn run_until_loop_or_termination(program: &[Inst]) -> RunResult {
-    let mut instructions_run = BTreeSet::new();
-    let mut emu = Emulator::init();
-    while !emu.should_terminate(program) {
-        if !instructions_run.insert(emu.pc) {
-            return RunResult::Looped(emu.acc);
+fn repair(path: Option<&String>) -> io::Result<()> {
+    let program = load(path)?;
+    if let RunResult::Terminated(acc) =
+        run_until_loop_or_termination(&program, DEFAULT_STEP_BUDGET, None)
+    {
+        println!("program already terminates with acc {}", acc);
+        return Ok(());
+    }
+    match repair::find_fixes(&program) {
+        Ok(fixes) if fixes.is_empty() => println!("no single-instruction fix found"),
+        Ok(fixes) => {
+            for fix in fixes {
+                let result =
+                    run_until_loop_or_termination(&fix.apply(&program), DEFAULT_STEP_BUDGET, None);
+                println!("{:>5}: {} -> {}, {}", fix.pc, fix.from, fix.to, result);
+            }
         }
-        emu.step(program);
+        Err(err) => println!(
+            "cannot analyse conditional jump at {}; control flow depends on acc",
+            err.pc
+        ),
     }
-    RunResult::Terminated(emu.acc)
+    Ok(())
 }
 
-fn part1() {
-    if let RunResult::Looped(res) = run_until_loop_or_termination(&PROGRAM) {
-        println!("*   {}", res);
+fn load(path: Option<&String>) -> io::Result<Vec<Inst>> {
+    match path {
+        Some(path) => Ok(vm::parse_program(&fs::read_to_string(path)?)),
+        None => Ok(PROGRAM.clone()),
     }
 }
 
-fn part2() {
-    for (i, inst) in PROGRAM.iter().enumerate() {
-        let mut program = PROGRAM.clone();
-        match *inst {
-            Inst::Jmp(arg) => program[i] = Inst::Nop(arg),
-            Inst::Nop(arg) => program[i] = Inst::Jmp(arg),
-            _ => continue,
+fn record(program: &[Inst]) -> Trace {
+    let mut trace = Trace::default();
+    run_until_loop_or_termination(program, DEFAULT_STEP_BUDGET, Some(&mut trace));
+    trace
+}
+
+fn export_trace(format: &str, path: Option<&String>) -> io::Result<()> {
+    if !matches!(format, "csv" | "json") {
+        eprintln!(
+            "error: unknown trace format {:?}, expected csv or json",
+            format
+        );
+        process::exit(1);
+    }
+    let trace = record(&load(path)?);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if format == "json" {
+        trace.write_json(&mut out)?;
+    } else {
+        trace.write_csv(&mut out)?;
+    }
+    out.flush()
+}
+
+fn profile(path: Option<&String>) -> io::Result<()> {
+    let program = load(path)?;
+    let hits = record(&program).profile();
+    for (pc, inst) in program.iter().enumerate() {
+        let count = hits.get(&(pc as isize)).copied().unwrap_or(0);
+        println!("{:>5}: {:<10} {:>6}", pc, inst.to_string(), count);
+    }
+    Ok(())
+}
+
+fn assemble(path: &str) -> io::Result<()> {
+    match asm::assemble(&fs::read_to_string(path)?) {
+        Ok(program) => {
+            for inst in program {
+                println!("{}", inst);
+            }
         }
-        if let RunResult::Terminated(res) = run_until_loop_or_termination(&program) {
-            println!("**  {}", res);
-            return;
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
         }
     }
+    Ok(())
 }
 
-fn main() {
-    part1();
-    part2();
+fn disassemble(path: Option<&String>) -> io::Result<()> {
+    print!("{}", asm::disassemble(&load(path)?));
+    Ok(())
+}
+
+fn optimize(path: Option<&String>) -> io::Result<()> {
+    let program = load(path)?;
+    let optimized = optimize::optimize(&program);
+    for inst in &optimized {
+        println!("{}", inst);
+    }
+    // dropping unreachable conditional jumps must not change how loops are
+    // detected, or a loop that overflows acc would be reported differently
+    let acc_affects_control_flow = vm::first_conditional_jump(&program).is_some();
+    let before = execute(
+        &program,
+        DEFAULT_STEP_BUDGET,
+        None,
+        acc_affects_control_flow,
+    );
+    let after = execute(
+        &optimized,
+        DEFAULT_STEP_BUDGET,
+        None,
+        acc_affects_control_flow,
+    );
+    eprintln!("{} -> {} instructions", program.len(), optimized.len());
+    eprintln!("original:  {}", before);
+    eprintln!("optimized: {}", after);
+    if !before.same_outcome(&after) {
+        eprintln!("error: optimized program behaves differently");
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn debug(path: Option<&String>) -> io::Result<()> {
+    let program = load(path)?;
+    let stdin = io::stdin();
+    Debugger::new(&program).repl(stdin.lock())
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [cmd, path] if cmd == "run" => run(path, DEFAULT_STEP_BUDGET),
+        [cmd, path, flag, budget] if cmd == "run" && flag == "--budget" => match budget.parse() {
+            Ok(budget) => run(path, budget),
+            Err(_) => {
+                eprintln!("error: --budget expects a number");
+                process::exit(1);
+            }
+        },
+        [cmd, rest @ ..] if cmd == "debug" => debug(rest.first()),
+        [cmd, format, rest @ ..] if cmd == "trace" => export_trace(format, rest.first()),
+        [cmd, rest @ ..] if cmd == "profile" => profile(rest.first()),
+        [cmd, rest @ ..] if cmd == "repair" => repair(rest.first()),
+        [cmd, path] if cmd == "asm" => assemble(path),
+        [cmd, rest @ ..] if cmd == "disasm" => disassemble(rest.first()),
+        [cmd, rest @ ..] if cmd == "optimize" => optimize(rest.first()),
+        _ => {
+            part1();
+            part2();
+            Ok(())
+        }
+    }
 }
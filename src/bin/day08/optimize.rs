@@ -0,0 +1,147 @@
+use crate::vm::Inst;
+use std::convert::TryFrom;
+
+fn target(pc: usize, inst: Inst) -> Option<isize> {
+    match inst {
+        Inst::Jmp(arg) | Inst::Jz(arg) | Inst::Jnz(arg) => (pc as isize).checked_add(arg),
+        _ => None,
+    }
+}
+
+fn in_bounds(program: &[Inst], target: isize) -> Option<usize> {
+    usize::try_from(target)
+        .ok()
+        .filter(|&target| target <= program.len())
+}
+
+fn successors(program: &[Inst], pc: usize) -> Vec<usize> {
+    let inst = program[pc];
+    let jump = target(pc, inst).and_then(|target| in_bounds(program, target));
+    match inst {
+        Inst::Acc(_) | Inst::Mul(_) | Inst::Nop(_) => vec![pc + 1],
+        Inst::Jmp(_) => jump.into_iter().collect(),
+        Inst::Jz(_) | Inst::Jnz(_) => jump.into_iter().chain(Some(pc + 1)).collect(),
+        Inst::Hlt | Inst::Invalid => Vec::new(),
+    }
+}
+
+fn reachable(program: &[Inst]) -> Vec<bool> {
+    let mut seen = vec![false; program.len() + 1];
+    let mut stack = vec![0];
+    while let Some(pc) = stack.pop() {
+        if seen[pc] {
+            continue;
+        }
+        seen[pc] = true;
+        if pc < program.len() {
+            stack.extend(successors(program, pc));
+        }
+    }
+    seen
+}
+
+fn jump_targets(program: &[Inst]) -> Vec<bool> {
+    let mut targets = vec![false; program.len() + 1];
+    for (pc, &inst) in program.iter().enumerate() {
+        if let Some(target) = target(pc, inst).and_then(|target| in_bounds(program, target)) {
+            targets[target] = true;
+        }
+    }
+    targets
+}
+
+fn remove(program: &[Inst], removed: &[bool]) -> Vec<Inst> {
+    let mut new_pc = Vec::with_capacity(program.len() + 1);
+    let mut kept = 0;
+    for &removed in removed.iter().take(program.len()) {
+        new_pc.push(kept as isize);
+        if !removed {
+            kept += 1;
+        }
+    }
+    new_pc.push(kept as isize);
+    program
+        .iter()
+        .enumerate()
+        .filter(|&(pc, _)| !removed[pc])
+        .map(|(pc, &inst)| {
+            // removing instructions only moves pc towards 0, so an out of
+            // bounds jump keeps its offset and stays out of bounds
+            let jump = target(pc, inst).and_then(|target| in_bounds(program, target));
+            let offset = |arg: isize| match jump {
+                Some(target) => new_pc[target] - new_pc[pc],
+                None => arg,
+            };
+            match inst {
+                Inst::Jmp(arg) => Inst::Jmp(offset(arg)),
+                Inst::Jz(arg) => Inst::Jz(offset(arg)),
+                Inst::Jnz(arg) => Inst::Jnz(offset(arg)),
+                inst => inst,
+            }
+        })
+        .collect()
+}
+
+fn remove_unreachable(program: &[Inst]) -> Vec<Inst> {
+    let reachable = reachable(program);
+    let removed: Vec<bool> = reachable.iter().map(|&r| !r).collect();
+    remove(program, &removed)
+}
+
+fn fold_accumulates(program: &[Inst]) -> Vec<Inst> {
+    let targets = jump_targets(program);
+    let mut program = program.to_vec();
+    let mut removed = vec![false; program.len()];
+    let mut pc = 0;
+    while pc < program.len() {
+        let mut next = pc + 1;
+        if let Inst::Acc(mut sum) = program[pc] {
+            while next < program.len() && !targets[next] {
+                match program[next] {
+                    // mixed signs could overflow part way and fault where
+                    // the folded sum would not
+                    Inst::Acc(arg)
+                        if sum.signum() * arg.signum() >= 0 && sum.checked_add(arg).is_some() =>
+                    {
+                        sum += arg;
+                        removed[next] = true;
+                        next += 1;
+                    }
+                    _ => break,
+                }
+            }
+            program[pc] = Inst::Acc(sum);
+        }
+        pc = next;
+    }
+    remove(&program, &removed)
+}
+
+fn remove_no_ops(program: &[Inst]) -> Vec<Inst> {
+    let removed: Vec<bool> = program
+        .iter()
+        .map(|inst| {
+            matches!(
+                inst,
+                Inst::Nop(_)
+                    | Inst::Acc(0)
+                    | Inst::Mul(1)
+                    | Inst::Jmp(1)
+                    | Inst::Jz(1)
+                    | Inst::Jnz(1)
+            )
+        })
+        .collect();
+    remove(program, &removed)
+}
+
+pub fn optimize(program: &[Inst]) -> Vec<Inst> {
+    let mut program = program.to_vec();
+    loop {
+        let optimized = remove_no_ops(&fold_accumulates(&remove_unreachable(&program)));
+        if optimized == program {
+            return program;
+        }
+        program = optimized;
+    }
+}
@@ -0,0 +1,94 @@
+use crate::vm::{self, Inst};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Fix {
+    pub pc: usize,
+    pub from: Inst,
+    pub to: Inst,
+}
+
+impl Fix {
+    pub fn apply(&self, program: &[Inst]) -> Vec<Inst> {
+        let mut program = program.to_vec();
+        program[self.pc] = self.to;
+        program
+    }
+}
+
+#[derive(Debug)]
+pub struct ConditionalJumpError {
+    pub pc: usize,
+}
+
+fn flipped(inst: Inst) -> Option<Inst> {
+    match inst {
+        Inst::Jmp(arg) => Some(Inst::Nop(arg)),
+        Inst::Nop(arg) => Some(Inst::Jmp(arg)),
+        _ => None,
+    }
+}
+
+fn target(pc: usize, offset: isize, len: usize) -> Option<usize> {
+    (pc as isize)
+        .checked_add(offset)
+        .and_then(|target| usize::try_from(target).ok())
+        .filter(|&target| target <= len)
+}
+
+fn successor(program: &[Inst], pc: usize, inst: Inst) -> Option<usize> {
+    let len = program.len();
+    match inst {
+        Inst::Acc(_) | Inst::Mul(_) | Inst::Nop(_) => Some(pc + 1),
+        Inst::Jmp(arg) => target(pc, arg, len),
+        Inst::Hlt => Some(len),
+        Inst::Jz(_) | Inst::Jnz(_) | Inst::Invalid => None,
+    }
+}
+
+fn reaches_end(program: &[Inst]) -> Vec<bool> {
+    let len = program.len();
+    let mut predecessors = vec![Vec::new(); len + 1];
+    for (pc, &inst) in program.iter().enumerate() {
+        if let Some(next) = successor(program, pc, inst) {
+            predecessors[next].push(pc);
+        }
+    }
+    let mut reaches = vec![false; len + 1];
+    reaches[len] = true;
+    let mut queue = VecDeque::from(vec![len]);
+    while let Some(pc) = queue.pop_front() {
+        for &prev in &predecessors[pc] {
+            if !reaches[prev] {
+                reaches[prev] = true;
+                queue.push_back(prev);
+            }
+        }
+    }
+    reaches
+}
+
+pub fn find_fixes(program: &[Inst]) -> Result<Vec<Fix>, ConditionalJumpError> {
+    if let Some(pc) = vm::first_conditional_jump(program) {
+        return Err(ConditionalJumpError { pc });
+    }
+    let reaches = reaches_end(program);
+    let mut fixes = Vec::new();
+    let mut visited = vec![false; program.len()];
+    let mut pc = 0;
+    while pc < program.len() && !reaches[pc] && !visited[pc] {
+        visited[pc] = true;
+        let inst = program[pc];
+        if let Some(to) = flipped(inst) {
+            if successor(program, pc, to).is_some_and(|next| reaches[next]) {
+                fixes.push(Fix { pc, from: inst, to });
+            }
+        }
+        match successor(program, pc, inst) {
+            Some(next) => pc = next,
+            None => break,
+        }
+    }
+    Ok(fixes)
+}
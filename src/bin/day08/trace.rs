@@ -0,0 +1,59 @@
+use crate::vm::{Inst, Registers};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+pub struct TraceEntry {
+    pub step: usize,
+    pub pc: isize,
+    pub inst: Inst,
+    pub acc: isize,
+}
+
+#[derive(Default)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn record(&mut self, before: Registers, inst: Inst, after: Registers) {
+        self.entries.push(TraceEntry {
+            step: self.entries.len(),
+            pc: before.pc,
+            inst,
+            acc: after.acc,
+        });
+    }
+
+    pub fn profile(&self) -> BTreeMap<isize, usize> {
+        let mut hits = BTreeMap::new();
+        for entry in &self.entries {
+            *hits.entry(entry.pc).or_insert(0) += 1;
+        }
+        hits
+    }
+
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "step,pc,instruction,acc")?;
+        for entry in &self.entries {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                entry.step, entry.pc, entry.inst, entry.acc
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[")?;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let separator = if i + 1 < self.entries.len() { "," } else { "" };
+            writeln!(
+                out,
+                "  {{\"step\": {}, \"pc\": {}, \"instruction\": \"{}\", \"acc\": {}}}{}",
+                entry.step, entry.pc, entry.inst, entry.acc, separator
+            )?;
+        }
+        writeln!(out, "]")
+    }
+}
@@ -0,0 +1,167 @@
+use either::Either;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Inst {
+    Acc(isize),
+    Jmp(isize),
+    Nop(isize),
+    Mul(isize),
+    Jz(isize),
+    Jnz(isize),
+    Hlt,
+    Invalid,
+}
+
+#[derive(Debug)]
+pub struct UnrecognizedInstError;
+
+impl FromStr for Inst {
+    type Err = Either<UnrecognizedInstError, std::num::ParseIntError>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "hlt" {
+            return Ok(Inst::Hlt);
+        }
+        let (inst, arg) = s
+            .split_once(' ')
+            .ok_or(Either::Left(UnrecognizedInstError))?;
+        let arg = arg.parse().map_err(Either::Right)?;
+        match inst {
+            "acc" => Ok(Inst::Acc(arg)),
+            "jmp" => Ok(Inst::Jmp(arg)),
+            "nop" => Ok(Inst::Nop(arg)),
+            "mul" => Ok(Inst::Mul(arg)),
+            "jz" => Ok(Inst::Jz(arg)),
+            "jnz" => Ok(Inst::Jnz(arg)),
+            _ => Err(Either::Left(UnrecognizedInstError)),
+        }
+    }
+}
+
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inst::Acc(arg) => write!(f, "acc {:+}", arg),
+            Inst::Jmp(arg) => write!(f, "jmp {:+}", arg),
+            Inst::Nop(arg) => write!(f, "nop {:+}", arg),
+            Inst::Mul(arg) => write!(f, "mul {:+}", arg),
+            Inst::Jz(arg) => write!(f, "jz {:+}", arg),
+            Inst::Jnz(arg) => write!(f, "jnz {:+}", arg),
+            Inst::Hlt => write!(f, "hlt"),
+            Inst::Invalid => write!(f, "???"),
+        }
+    }
+}
+
+pub fn parse_program(source: &str) -> Vec<Inst> {
+    source
+        .lines()
+        .map(|line| line.parse().unwrap_or(Inst::Invalid))
+        .collect()
+}
+
+pub fn first_conditional_jump(program: &[Inst]) -> Option<usize> {
+    program
+        .iter()
+        .position(|inst| matches!(inst, Inst::Jz(_) | Inst::Jnz(_)))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Registers {
+    pub pc: isize,
+    pub acc: isize,
+}
+
+impl Registers {
+    pub fn loop_state(self, acc_affects_control_flow: bool) -> Self {
+        if acc_affects_control_flow {
+            self
+        } else {
+            Self { acc: 0, ..self }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Halted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    OutOfBounds { pc: isize, target: isize },
+    InvalidInstruction { pc: isize },
+    Overflow { pc: isize },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::OutOfBounds { pc, target } => {
+                write!(f, "jump from {} to out-of-bounds address {}", pc, target)
+            }
+            Fault::InvalidInstruction { pc } => write!(f, "invalid instruction at {}", pc),
+            Fault::Overflow { pc } => write!(f, "accumulator overflow at {}", pc),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Vm {
+    pub regs: Registers,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fetch(&self, program: &[Inst]) -> Option<Inst> {
+        usize::try_from(self.regs.pc)
+            .ok()
+            .and_then(|pc| program.get(pc))
+            .copied()
+    }
+
+    pub fn step(&mut self, program: &[Inst]) -> Result<Status, Fault> {
+        let pc = self.regs.pc;
+        let inst = match self.fetch(program) {
+            Some(inst) => inst,
+            None => return Ok(Status::Halted),
+        };
+        let overflow = Fault::Overflow { pc };
+        let offset = match inst {
+            Inst::Acc(arg) => {
+                self.regs.acc = self.regs.acc.checked_add(arg).ok_or(overflow)?;
+                1
+            }
+            Inst::Mul(arg) => {
+                self.regs.acc = self.regs.acc.checked_mul(arg).ok_or(overflow)?;
+                1
+            }
+            Inst::Jmp(arg) => arg,
+            Inst::Jz(arg) if self.regs.acc == 0 => arg,
+            Inst::Jnz(arg) if self.regs.acc != 0 => arg,
+            Inst::Nop(_) | Inst::Jz(_) | Inst::Jnz(_) => 1,
+            Inst::Hlt => return Ok(Status::Halted),
+            Inst::Invalid => return Err(Fault::InvalidInstruction { pc }),
+        };
+        let target = pc.checked_add(offset).ok_or(Fault::OutOfBounds {
+            pc,
+            target: pc.saturating_add(offset),
+        })?;
+        if target < 0 || target as usize > program.len() {
+            return Err(Fault::OutOfBounds { pc, target });
+        }
+        self.regs.pc = target;
+        if target as usize == program.len() {
+            Ok(Status::Halted)
+        } else {
+            Ok(Status::Running)
+        }
+    }
+}
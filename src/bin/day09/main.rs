@@ -1,5 +1,10 @@
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::ops::Range;
+use std::process;
 
 lazy_static! {
     static ref DATA: Vec<usize> = include_str!("input.txt")
@@ -8,45 +13,204 @@ lazy_static! {
         .collect();
 }
 
-const PREAMBLE_SIZE: usize = 25;
-
-fn invalid_value() -> usize {
-    *(DATA
-        .windows(PREAMBLE_SIZE + 1)
-        .find_map(|window| {
-            let (value, preamble) = window.split_last().unwrap();
-            if preamble
-                .iter()
-                .tuple_combinations()
-                .any(|(a, b)| a + b == *value)
-            {
-                None
-            } else {
-                Some(value)
+const DEFAULT_PREAMBLE_SIZE: usize = 25;
+
+struct XmasValidator {
+    preamble_size: usize,
+    window: VecDeque<usize>,
+    pair_sums: HashMap<usize, usize>,
+}
+
+impl XmasValidator {
+    fn new(preamble_size: usize) -> Self {
+        Self {
+            preamble_size,
+            window: VecDeque::with_capacity(preamble_size + 1),
+            pair_sums: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, value: usize) -> bool {
+        let valid = self.window.len() < self.preamble_size || self.pair_sums.contains_key(&value);
+        for &other in &self.window {
+            *self.pair_sums.entry(other + value).or_insert(0) += 1;
+        }
+        self.window.push_back(value);
+        if self.window.len() > self.preamble_size {
+            let oldest = self.window.pop_front().unwrap();
+            for &other in &self.window {
+                let sum = oldest + other;
+                let count = self.pair_sums.get_mut(&sum).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.pair_sums.remove(&sum);
+                }
             }
-        })
-        .unwrap())
+        }
+        valid
+    }
+}
+
+fn invalid_values(
+    values: impl IntoIterator<Item = usize>,
+    preamble_size: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let mut validator = XmasValidator::new(preamble_size);
+    values
+        .into_iter()
+        .enumerate()
+        .filter(move |&(_, value)| !validator.push(value))
 }
 
-fn part1() {
-    println!("*   {}", invalid_value());
+fn invalid_value(data: &[usize], preamble_size: usize) -> Option<usize> {
+    invalid_values(data.iter().copied(), preamble_size)
+        .next()
+        .map(|(_, value)| value)
 }
 
-fn part2() {
-    let invalid_value = invalid_value();
-    for range_size in 2.. {
-        if let Some(range) = DATA
-            .windows(range_size)
-            .find(|range| range.iter().sum::<usize>() == invalid_value)
+fn scan(path: Option<&String>, preamble_size: usize) -> io::Result<()> {
+    let reader: Box<dyn BufRead> = match path {
+        Some(path) if path != "-" => Box::new(BufReader::new(File::open(path)?)),
+        _ => Box::new(io::stdin().lock()),
+    };
+    let mut validator = XmasValidator::new(preamble_size);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_no = i + 1;
+        match line.trim().parse() {
+            Ok(value) if !validator.push(value) => println!("{:>6}  {}", line_no, value),
+            Ok(_) => {}
+            Err(_) if line.trim().is_empty() => {}
+            Err(_) => eprintln!("line {}: skipping {:?}", line_no, line),
+        }
+    }
+    Ok(())
+}
+
+// shortest range first, leftmost among equals, like trying each size in turn
+fn contiguous_range(data: &[usize], target: usize) -> Option<&[usize]> {
+    let mut shortest: Option<&[usize]> = None;
+    let mut start = 0;
+    let mut sum = 0;
+    for end in 0..data.len() {
+        sum += data[end];
+        while start < end
+            && (sum > target || (sum == target && data[start] == 0 && end - start > 1))
+        {
+            sum -= data[start];
+            start += 1;
+        }
+        if sum == target
+            && end > start
+            && shortest.is_none_or(|range| end - start + 1 < range.len())
         {
-            let (min, max) = range.iter().minmax().into_option().unwrap();
-            println!("**  {}", min + max);
-            return;
+            shortest = Some(&data[start..=end]);
         }
     }
+    shortest
+}
+
+fn contiguous_ranges(data: &[usize], target: usize) -> Vec<Range<usize>> {
+    let mut starts: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut ranges = Vec::new();
+    let mut prefix: usize = 0;
+    for end in 0..=data.len() {
+        if let Some(candidates) = prefix.checked_sub(target).and_then(|p| starts.get(&p)) {
+            ranges.extend(
+                candidates
+                    .iter()
+                    .filter(|&&start| end - start >= 2)
+                    .map(|&start| start..end),
+            );
+        }
+        starts.entry(prefix).or_default().push(end);
+        if let Some(value) = data.get(end) {
+            prefix += value;
+        }
+    }
+    ranges.sort_by_key(|range| (range.start, range.end));
+    ranges
+}
+
+fn weakness(range: &[usize]) -> usize {
+    let (min, max) = range.iter().minmax().into_option().unwrap();
+    min + max
+}
+
+fn exploits(target: Option<&String>, preamble_size: usize) -> Result<(), String> {
+    let target = match target {
+        Some(target) => target
+            .parse()
+            .map_err(|_| format!("target {:?} is not a number", target))?,
+        None => invalid_value(&DATA, preamble_size).ok_or_else(|| {
+            format!(
+                "no invalid value with preamble {}, give a target to search for",
+                preamble_size
+            )
+        })?,
+    };
+    let ranges = contiguous_ranges(&DATA, target);
+    println!("{} ranges sum to {}", ranges.len(), target);
+    for range in ranges {
+        println!(
+            "{:>6}..{:<6} length {:>4}  weakness {}",
+            range.start,
+            range.end,
+            range.len(),
+            weakness(&DATA[range.clone()])
+        );
+    }
+    Ok(())
+}
+
+fn part1(preamble_size: usize) {
+    match invalid_value(&DATA, preamble_size) {
+        Some(value) => println!("*   {}", value),
+        None => println!("*   no invalid value with preamble {}", preamble_size),
+    }
+}
+
+fn part2(preamble_size: usize) {
+    match invalid_value(&DATA, preamble_size) {
+        Some(value) => match contiguous_range(&DATA, value) {
+            Some(range) => println!("**  {}", weakness(range)),
+            None => println!("**  no contiguous range sums to {}", value),
+        },
+        None => println!("**  no invalid value with preamble {}", preamble_size),
+    }
 }
 
 fn main() {
-    part1();
-    part2();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let preamble_size = match args.iter().position(|arg| arg == "--preamble") {
+        Some(i) => match args.get(i + 1).map(|n| n.parse()) {
+            Some(Ok(n)) => {
+                args.drain(i..=i + 1);
+                n
+            }
+            _ => {
+                eprintln!("error: --preamble expects a size");
+                process::exit(1);
+            }
+        },
+        None => DEFAULT_PREAMBLE_SIZE,
+    };
+    match args.as_slice() {
+        [cmd, rest @ ..] if cmd == "scan" => {
+            if let Err(err) = scan(rest.first(), preamble_size) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+        }
+        [cmd, rest @ ..] if cmd == "exploits" => {
+            if let Err(err) = exploits(rest.first(), preamble_size) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+        }
+        _ => {
+            part1(preamble_size);
+            part2(preamble_size);
+        }
+    }
 }
@@ -1,41 +1,323 @@
-#![feature(map_first_last)]
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use num::{BigUint, One, Zero};
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 lazy_static! {
     static ref ADAPTERS: BTreeSet<usize> = include_str!("input.txt")
         .lines()
         .flat_map(|line| line.parse())
         .collect();
-    static ref LOWEST_JOLTAGE: usize = *ADAPTERS.first().unwrap();
-    static ref BUILT_IN_JOLTAGE: usize = ADAPTERS.last().unwrap() + 3;
 }
 
-fn part1() {
-    let mut differences = BTreeMap::new();
-    differences.insert(*LOWEST_JOLTAGE, 1);
-    // built in joltage is always 3 more than the highest adapter
-    differences.insert(3, 1);
-    for (a, b) in ADAPTERS.iter().tuple_windows() {
-        *differences.entry(b - a).or_default() += 1;
+struct ChainConfig {
+    gaps: RangeInclusive<usize>,
+    device_offset: usize,
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            gaps: 1..=3,
+            // the puzzle's device is rated 3 jolts above the highest adapter
+            device_offset: 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ChainError {
+    Gap { from: usize, to: usize },
+    NoArrangement,
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::Gap { from, to } => write!(
+                f,
+                "no valid chain: cannot connect {} jolts to {} jolts",
+                from, to
+            ),
+            ChainError::NoArrangement => write!(f, "no arrangement reaches the device"),
+        }
+    }
+}
+
+impl ChainConfig {
+    fn device_joltage(&self, adapters: &BTreeSet<usize>) -> usize {
+        adapters.iter().next_back().unwrap_or(&0) + self.device_offset
+    }
+
+    fn chain(&self, adapters: &BTreeSet<usize>) -> Vec<usize> {
+        std::iter::once(0)
+            .chain(adapters.iter().copied())
+            .chain(Some(self.device_joltage(adapters)))
+            .collect()
+    }
+
+    fn differences(
+        &self,
+        adapters: &BTreeSet<usize>,
+    ) -> Result<BTreeMap<usize, usize>, ChainError> {
+        let mut differences = BTreeMap::new();
+        for (a, b) in self.chain(adapters).into_iter().tuple_windows() {
+            if !self.gaps.contains(&(b - a)) {
+                return Err(ChainError::Gap { from: a, to: b });
+            }
+            *differences.entry(b - a).or_insert(0) += 1;
+        }
+        Ok(differences)
+    }
+
+    // ways to reach the device from each joltage in the chain
+    fn path_counts(&self, adapters: &BTreeSet<usize>) -> BTreeMap<usize, BigUint> {
+        let device = self.device_joltage(adapters);
+        let mut paths = BTreeMap::new();
+        paths.insert(device, BigUint::one());
+        for adapter in adapters.iter().copied().rev().chain(Some(0)) {
+            let count = self
+                .gaps
+                .clone()
+                .filter_map(|i| paths.get(&(adapter + i)))
+                .sum();
+            paths.insert(adapter, count);
+        }
+        paths
+    }
+
+    fn arrangements(&self, adapters: &BTreeSet<usize>) -> Result<BigUint, ChainError> {
+        match self.path_counts(adapters).remove(&0) {
+            Some(count) if !count.is_zero() => Ok(count),
+            _ => Err(ChainError::NoArrangement),
+        }
+    }
+
+    fn enumerate<'a>(&'a self, adapters: &BTreeSet<usize>) -> Arrangements<'a> {
+        let paths = self.path_counts(adapters);
+        let stack = if paths[&0].is_zero() {
+            Vec::new()
+        } else {
+            vec![(0, *self.gaps.start())]
+        };
+        Arrangements {
+            gaps: &self.gaps,
+            device: self.device_joltage(adapters),
+            paths,
+            stack,
+        }
+    }
+
+    // walks down the path counts to the arrangement at index in enumeration order
+    fn nth_arrangement(&self, paths: &BTreeMap<usize, BigUint>, mut index: BigUint) -> Vec<usize> {
+        let device = *paths.keys().next_back().unwrap();
+        let mut chain = vec![0];
+        let mut joltage = 0;
+        while joltage != device {
+            for gap in self.gaps.clone() {
+                let count = match paths.get(&(joltage + gap)) {
+                    Some(count) => count,
+                    None => continue,
+                };
+                if index < *count {
+                    joltage += gap;
+                    break;
+                }
+                index -= count;
+            }
+            chain.push(joltage);
+        }
+        chain
+    }
+}
+
+// depth first, skipping joltages that cannot reach the device
+struct Arrangements<'a> {
+    gaps: &'a RangeInclusive<usize>,
+    device: usize,
+    paths: BTreeMap<usize, BigUint>,
+    stack: Vec<(usize, usize)>,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (joltage, gap) = self.stack.last_mut()?;
+            if *gap > *self.gaps.end() {
+                self.stack.pop();
+                continue;
+            }
+            let next = *joltage + *gap;
+            *gap += 1;
+            if next == self.device {
+                let mut chain: Vec<usize> = self.stack.iter().map(|&(j, _)| j).collect();
+                chain.push(next);
+                return Some(chain);
+            }
+            if self.paths.get(&next).is_some_and(|count| !count.is_zero()) {
+                self.stack.push((next, *self.gaps.start()));
+            }
+        }
+    }
+}
+
+// xorshift64*
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // uniform in 0..bound by rejection sampling
+    fn below(&mut self, bound: &BigUint) -> BigUint {
+        let bits = bound.bits();
+        let words = bits.div_ceil(64);
+        loop {
+            let digits = (0..words)
+                .flat_map(|_| {
+                    let word = self.next_u64();
+                    vec![word as u32, (word >> 32) as u32]
+                })
+                .collect();
+            let candidate = BigUint::new(digits) >> (words * 64 - bits);
+            if candidate < *bound {
+                return candidate;
+            }
+        }
+    }
+}
+
+fn format_chain(chain: &[usize]) -> String {
+    chain.iter().join(" ")
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("{:?} is not a number", s))
+}
+
+fn list(config: &ChainConfig, limit: Option<&String>) -> Result<(), String> {
+    let limit = limit.map_or(Ok(10), |n| parse_number(n))?;
+    for chain in config.enumerate(&ADAPTERS).take(limit) {
+        println!("{}", format_chain(&chain));
     }
-    println!("*   {}", differences[&1] * differences[&3]);
+    Ok(())
 }
 
-fn part2() {
-    let mut paths = BTreeMap::new();
-    paths.insert(*BUILT_IN_JOLTAGE, 1usize);
-    for adapter in ADAPTERS.iter().copied().rev().chain(Some(0)) {
-        paths.insert(
-            adapter,
-            (1..=3).filter_map(|i| paths.get(&(adapter + i))).sum(),
-        );
+fn sample(config: &ChainConfig, args: &[String]) -> Result<(), String> {
+    let count = args.first().map_or(Ok(1), |n| parse_number(n))?;
+    let seed = match args.get(1) {
+        Some(seed) => parse_number(seed)?,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64,
+    };
+    let total = match config.arrangements(&ADAPTERS) {
+        Ok(total) => total,
+        Err(err) => {
+            println!("{}", err);
+            return Ok(());
+        }
+    };
+    let paths = config.path_counts(&ADAPTERS);
+    let mut rng = Rng::new(seed);
+    for _ in 0..count {
+        let index = rng.below(&total);
+        println!("{}", format_chain(&config.nth_arrangement(&paths, index)));
+    }
+    Ok(())
+}
+
+fn part1(config: &ChainConfig) {
+    match config.differences(&ADAPTERS) {
+        Ok(differences) => {
+            let count = |gap| differences.get(gap).copied().unwrap_or(0);
+            println!(
+                "*   {}",
+                count(config.gaps.start()) * count(config.gaps.end())
+            );
+        }
+        Err(err) => println!("*   {}", err),
+    }
+}
+
+fn part2(config: &ChainConfig) {
+    match config.arrangements(&ADAPTERS) {
+        Ok(count) => println!("**  {}", count),
+        Err(err) => println!("**  {}", err),
+    }
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, String> {
+    let i = match args.iter().position(|arg| arg == flag) {
+        Some(i) if i + 1 < args.len() => i,
+        Some(_) => return Err(format!("{} expects a value", flag)),
+        None => return Ok(None),
+    };
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+fn parse_gaps(gaps: &str) -> Option<RangeInclusive<usize>> {
+    let (lo, hi) = gaps.split_once('-')?;
+    let (lo, hi) = (lo.parse().ok()?, hi.parse().ok()?);
+    (0 < lo && lo <= hi).then_some(lo..=hi)
+}
+
+fn parse_config(args: &mut Vec<String>) -> Result<ChainConfig, String> {
+    let mut config = ChainConfig::default();
+    if let Some(gaps) = take_flag(args, "--gaps")? {
+        config.gaps = parse_gaps(&gaps)
+            .ok_or_else(|| format!("--gaps expects LO-HI with 1 <= LO <= HI, got {:?}", gaps))?;
+    }
+    if let Some(offset) = take_flag(args, "--offset")? {
+        config.device_offset = offset
+            .parse()
+            .map_err(|_| format!("--offset expects a number, got {:?}", offset))?;
+    }
+    if !config.gaps.contains(&config.device_offset) {
+        return Err(format!(
+            "device offset {} is outside the allowed gaps {}-{}",
+            config.device_offset,
+            config.gaps.start(),
+            config.gaps.end()
+        ));
+    }
+    Ok(config)
+}
+
+fn run(args: &mut Vec<String>) -> Result<(), String> {
+    let config = parse_config(args)?;
+    match args.as_slice() {
+        [cmd, rest @ ..] if cmd == "list" => list(&config, rest.first()),
+        [cmd, rest @ ..] if cmd == "sample" => sample(&config, rest),
+        _ => {
+            part1(&config);
+            part2(&config);
+            Ok(())
+        }
     }
-    println!("**  {}", paths[&0]);
 }
 
 fn main() {
-    part1();
-    part2();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(&mut args) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
 }